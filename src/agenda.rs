@@ -0,0 +1,66 @@
+use chrono::{Days, Local, NaiveDate};
+use rusqlite::{Connection, Result as SqlResult};
+
+use crate::note::{self, Note};
+use crate::todo::{self, Todo};
+use crate::utils::day_bounds;
+
+pub struct AgendaDay {
+    pub date: NaiveDate,
+    pub todos: Vec<Todo>,
+    pub daily_note: Option<Note>,
+}
+
+pub struct Agenda {
+    pub today: NaiveDate,
+    pub overdue: Vec<Todo>,
+    pub days: Vec<AgendaDay>,
+}
+
+/// Collect overdue TODOs plus TODOs and daily notes for each of the next
+/// `days` days, starting today.
+pub fn build(conn: &Connection, days: u32) -> SqlResult<Agenda> {
+    let today = Local::now().date_naive();
+    let last = today
+        .checked_add_days(Days::new(u64::from(days.max(1)) - 1))
+        .unwrap_or(NaiveDate::MAX);
+    let (today_start, _) = day_bounds(today);
+    let (_, last_end) = day_bounds(last);
+
+    let mut overdue = Vec::new();
+    let mut upcoming = Vec::new();
+    for t in todo::list_due_until(conn, last_end)? {
+        match t.due_date {
+            Some(due) if due.timestamp() < today_start => overdue.push(t),
+            Some(_) => upcoming.push(t),
+            None => {}
+        }
+    }
+
+    let mut dailies = note::list_daily(conn, today, last)?;
+
+    let mut agenda_days = Vec::new();
+    for date in today.iter_days().take_while(|d| *d <= last) {
+        let (todos, rest): (Vec<Todo>, Vec<Todo>) = upcoming
+            .into_iter()
+            .partition(|t| t.due_date.map(|d| d.date_naive()) == Some(date));
+        upcoming = rest;
+
+        let daily_note = dailies
+            .iter()
+            .position(|n| n.created_at.date_naive() == date)
+            .map(|i| dailies.remove(i));
+
+        agenda_days.push(AgendaDay {
+            date,
+            todos,
+            daily_note,
+        });
+    }
+
+    Ok(Agenda {
+        today,
+        overdue,
+        days: agenda_days,
+    })
+}
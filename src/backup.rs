@@ -0,0 +1,186 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local};
+use rusqlite::{Connection, DatabaseName, OpenFlags};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Snapshots in the backup directory are named `notes-<timestamp>[-<label>].db`;
+/// only files matching this are listed and rotated.
+const PREFIX: &str = "notes-";
+const EXTENSION: &str = "db";
+
+pub struct BackupInfo {
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: DateTime<Local>,
+}
+
+/// Copy the live database to `dest` with SQLite's online backup API, which
+/// yields a consistent snapshot even while other connections write through
+/// the WAL.
+pub fn snapshot(conn: &Connection, dest: &Path) -> Result<()> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if dest.exists() {
+        bail!("{} already exists", dest.display());
+    }
+
+    let mut dst = Connection::open(dest)?;
+    let backup = rusqlite::backup::Backup::new(conn, &mut dst)?;
+    backup.run_to_completion(256, Duration::from_millis(10), None)?;
+    drop(backup);
+
+    // The copy inherits WAL mode from the live database; switch it back so
+    // the snapshot is one self-contained file that reading doesn't modify
+    dst.execute_batch("PRAGMA journal_mode = DELETE;")?;
+    Ok(())
+}
+
+/// Snapshot into `dir` under a timestamped name, then delete all but the
+/// newest `keep` snapshots there. Returns the new snapshot's path.
+pub fn create_in(conn: &Connection, dir: &Path, label: Option<&str>, keep: usize) -> Result<PathBuf> {
+    let path = snapshot_in(conn, dir, label)?;
+    rotate(dir, keep, None)?;
+    Ok(path)
+}
+
+fn snapshot_in(conn: &Connection, dir: &Path, label: Option<&str>) -> Result<PathBuf> {
+    let mut name = format!("{}{}", PREFIX, Local::now().format("%Y%m%d-%H%M%S"));
+    if let Some(label) = label {
+        name.push('-');
+        name.push_str(label);
+    }
+
+    // Two snapshots in the same second get a counter rather than failing
+    let mut path = dir.join(format!("{}.{}", name, EXTENSION));
+    let mut n = 2;
+    while path.exists() {
+        path = dir.join(format!("{}-{}.{}", name, n, EXTENSION));
+        n += 1;
+    }
+
+    snapshot(conn, &path)?;
+    Ok(path)
+}
+
+/// Snapshots in `dir`, newest first.
+pub fn list(dir: &Path) -> Result<Vec<BackupInfo>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(_) => return Ok(Vec::new()),
+    };
+
+    let mut backups = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let is_snapshot = path.extension().is_some_and(|e| e == EXTENSION)
+            && path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(PREFIX));
+        if !is_snapshot {
+            continue;
+        }
+        let meta = fs::metadata(&path)?;
+        backups.push(BackupInfo {
+            path,
+            size: meta.len(),
+            modified: DateTime::from(meta.modified()?),
+        });
+    }
+
+    backups.sort_by(|a, b| b.modified.cmp(&a.modified).then(b.path.cmp(&a.path)));
+    Ok(backups)
+}
+
+/// Delete all but the newest `keep` snapshots in `dir`, never `spare`.
+fn rotate(dir: &Path, keep: usize, spare: Option<&Path>) -> Result<()> {
+    let spare = spare.and_then(|p| fs::canonicalize(p).ok());
+    for old in list(dir)?.into_iter().skip(keep.max(1)) {
+        if spare.is_some() && fs::canonicalize(&old.path).ok() == spare {
+            continue;
+        }
+        fs::remove_file(&old.path)?;
+    }
+    Ok(())
+}
+
+/// Find a snapshot by path, or by file name within the backup directory.
+pub fn resolve(dir: &Path, name: &str) -> Option<PathBuf> {
+    let direct = PathBuf::from(name);
+    if direct.is_file() {
+        return Some(direct);
+    }
+    [dir.join(name), dir.join(format!("{}.{}", name, EXTENSION))]
+        .into_iter()
+        .find(|p| p.is_file())
+}
+
+/// Save the live database to a "pre-restore" snapshot in `dir`, then replace
+/// it with `src`. Rotation runs only once the restore succeeded and never
+/// removes `src`. Returns the pre-restore snapshot's path.
+pub fn restore_in(conn: &mut Connection, dir: &Path, src: &Path, keep: usize) -> Result<PathBuf> {
+    let safety = snapshot_in(conn, dir, Some("pre-restore"))?;
+    restore(conn, src)?;
+    rotate(dir, keep, Some(src))?;
+    Ok(safety)
+}
+
+/// Replace the live database with the snapshot at `src`, after checking that
+/// it is a notectl database.
+pub fn restore(conn: &mut Connection, src: &Path) -> Result<()> {
+    let check = Connection::open_with_flags(src, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("cannot open {}", src.display()))?;
+    let is_notectl: bool = check
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'notes'",
+            [],
+            |row| row.get(0),
+        )
+        .with_context(|| format!("{} is not a SQLite database", src.display()))?;
+    if !is_notectl {
+        bail!("{} is not a notectl database", src.display());
+    }
+    drop(check);
+
+    conn.restore(DatabaseName::Main, src, None::<fn(rusqlite::backup::Progress)>)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note_count(conn: &Connection) -> i64 {
+        conn.query_row("SELECT COUNT(*) FROM notes", [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn restoring_the_oldest_snapshot_keeps_it() {
+        let dir = std::env::temp_dir().join(format!("notectl-backup-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        crate::db::initialize(&conn).unwrap();
+        for label in ["a", "b", "c"] {
+            crate::note::add(&conn, label, &[], None, false).unwrap();
+            create_in(&conn, &dir, Some(label), 3).unwrap();
+        }
+        let snapshots = list(&dir).unwrap();
+        assert_eq!(snapshots.len(), 3);
+        let oldest = snapshots
+            .iter()
+            .map(|b| b.path.clone())
+            .find(|p| p.file_stem().unwrap().to_string_lossy().ends_with("-a"))
+            .unwrap();
+
+        let safety = restore_in(&mut conn, &dir, &oldest, 3).unwrap();
+        assert_eq!(note_count(&conn), 1);
+        assert!(oldest.exists());
+        assert!(safety.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
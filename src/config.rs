@@ -0,0 +1,141 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::db::get_db_dir;
+
+/// Settings read from `~/.notectl/config.toml`. Every section is optional;
+/// missing keys fall back to the defaults below.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub board: BoardConfig,
+    pub templates: TemplatesConfig,
+    pub daily: DailyConfig,
+    pub weekly: PeriodicConfig,
+    pub monthly: PeriodicConfig,
+    pub backup: BackupConfig,
+    pub attachments: AttachmentsConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct AttachmentsConfig {
+    /// Directory for imported attachments; defaults to `~/.notectl/attachments`
+    pub dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    /// Directory for snapshots; defaults to `~/.notectl/backups`
+    pub dir: Option<PathBuf>,
+    /// Number of snapshots kept in the backup directory
+    pub keep: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        BackupConfig {
+            dir: None,
+            keep: 10,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PeriodicConfig {
+    /// Template used for new notes instead of the built-in skeleton
+    pub template: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct DailyConfig {
+    /// Template used for new daily notes instead of the built-in skeleton
+    pub template: Option<String>,
+    /// Copy unchecked items from the previous daily note into a new one
+    pub carry_over: bool,
+}
+
+impl Default for DailyConfig {
+    fn default() -> Self {
+        DailyConfig {
+            template: None,
+            carry_over: true,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct TemplatesConfig {
+    /// Directory of `.md` templates; defaults to `~/.notectl/templates`
+    pub dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct BoardConfig {
+    /// Workflow statuses shown as board columns, left to right
+    pub columns: Vec<String>,
+    /// Maximum number of TODOs allowed in a status at once
+    pub wip_limits: HashMap<String, usize>,
+}
+
+impl Default for BoardConfig {
+    fn default() -> Self {
+        BoardConfig {
+            columns: ["todo", "doing", "waiting", "done"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            wip_limits: HashMap::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn templates_dir(&self) -> PathBuf {
+        match self.templates.dir {
+            Some(ref dir) => expand_home(dir),
+            None => get_db_dir().join("templates"),
+        }
+    }
+
+    pub fn backups_dir(&self) -> PathBuf {
+        match self.backup.dir {
+            Some(ref dir) => expand_home(dir),
+            None => get_db_dir().join("backups"),
+        }
+    }
+
+    pub fn attachments_dir(&self) -> PathBuf {
+        match self.attachments.dir {
+            Some(ref dir) => expand_home(dir),
+            None => get_db_dir().join("attachments"),
+        }
+    }
+}
+
+/// Expand a leading `~/` so config paths can be written the way the README
+/// shows them.
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+pub fn get_config_path() -> PathBuf {
+    get_db_dir().join("config.toml")
+}
+
+pub fn load() -> Result<Config, toml::de::Error> {
+    match fs::read_to_string(get_config_path()) {
+        Ok(content) => toml::from_str(&content),
+        Err(_) => Ok(Config::default()),
+    }
+}
@@ -0,0 +1,414 @@
+use anyhow::{bail, Context, Result};
+use chrono::{Local, NaiveDate};
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+use crate::export::EXPORT_TIME_FORMAT;
+use crate::import::parse_time;
+use crate::note::Note;
+use crate::todo::{self, is_closed_status, ImportReport, Todo, TodoFilter, STATUSES};
+use crate::utils::{day_bounds, note_title, timestamp_to_local};
+
+pub const NOTE_COLUMNS: [&str; 8] = [
+    "id", "created", "updated", "title", "content", "tags", "category", "daily",
+];
+const DEFAULT_NOTE_COLUMNS: [&str; 6] = ["id", "created", "updated", "tags", "category", "content"];
+
+pub const TODO_COLUMNS: [&str; 13] = [
+    "id",
+    "task",
+    "status",
+    "priority",
+    "due",
+    "start",
+    "created",
+    "completed",
+    "project",
+    "tags",
+    "note",
+    "blocked_by",
+    "tracked_minutes",
+];
+const DEFAULT_TODO_COLUMNS: [&str; 9] = [
+    "id", "task", "status", "priority", "due", "project", "tags", "created", "completed",
+];
+
+/// Fields `import_todos` can fill; the rest of `TODO_COLUMNS` are derived
+/// from the database and ignored on import.
+const TODO_IMPORT_FIELDS: [&str; 9] = [
+    "task", "status", "priority", "due", "start", "created", "completed", "project", "tags",
+];
+
+/// Notes as CSV, one row each. `columns` is a comma-separated list of
+/// `NOTE_COLUMNS`; by default everything but the title is written. Fields
+/// containing commas, quotes or newlines are quoted, so multi-line notes
+/// stay in one cell, and text that looks like a formula is escaped.
+pub fn write_notes(notes: &[Note], columns: Option<&str>) -> Result<String> {
+    let columns = select_columns(columns, &NOTE_COLUMNS, &DEFAULT_NOTE_COLUMNS)?;
+    let mut out = csv::Writer::from_writer(Vec::new());
+    out.write_record(&columns)?;
+
+    for n in notes {
+        out.write_record(columns.iter().map(|c| match *c {
+            "id" => n.id.to_string(),
+            "created" => n.created_at.format(EXPORT_TIME_FORMAT).to_string(),
+            "updated" => n.updated_at.format(EXPORT_TIME_FORMAT).to_string(),
+            "title" => note_title(&n.content).to_string(),
+            "content" => n.content.clone(),
+            "tags" => n.tags.join(", "),
+            "category" => n.category.clone().unwrap_or_default(),
+            "daily" => n.is_daily.to_string(),
+            _ => String::new(),
+        }).map(cell))?;
+    }
+
+    finish(out)
+}
+
+/// Every TODO, archived and deferred ones included, as CSV.
+pub fn write_todos(conn: &Connection, columns: Option<&str>) -> Result<String> {
+    let columns = select_columns(columns, &TODO_COLUMNS, &DEFAULT_TODO_COLUMNS)?;
+    let filter = TodoFilter {
+        include_deferred: true,
+        archived: None,
+        ..Default::default()
+    };
+    let mut todos = todo::list_todos(conn, &filter)?;
+    todos.sort_by_key(|t| t.id);
+
+    let mut out = csv::Writer::from_writer(Vec::new());
+    out.write_record(&columns)?;
+
+    let date = |d: Option<chrono::DateTime<Local>>| d.map(|d| d.format("%Y-%m-%d").to_string());
+    for t in &todos {
+        out.write_record(columns.iter().map(|c| match *c {
+            "id" => t.id.to_string(),
+            "task" => t.task.clone(),
+            "status" => t.status.clone(),
+            "priority" => t.priority.clone(),
+            "due" => date(t.due_date).unwrap_or_default(),
+            "start" => date(t.defer_until).unwrap_or_default(),
+            "created" => t.created_at.format(EXPORT_TIME_FORMAT).to_string(),
+            "completed" => t
+                .completed_at
+                .map(|d| d.format(EXPORT_TIME_FORMAT).to_string())
+                .unwrap_or_default(),
+            "project" => t.project.clone().unwrap_or_default(),
+            "tags" => t.tags.join(", "),
+            "note" => t.note_id.map(|id| id.to_string()).unwrap_or_default(),
+            "blocked_by" => t
+                .blocked_by
+                .iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            "tracked_minutes" => (t.tracked_secs / 60).to_string(),
+            _ => String::new(),
+        }).map(cell))?;
+    }
+
+    finish(out)
+}
+
+/// Whether a spreadsheet would evaluate `value` as a formula: it starts
+/// with `=`, `@`, a tab or a carriage return, or with `+`/`-` followed by
+/// something other than a space or a number, so list items (`- item`) and
+/// signed numbers stay as they are.
+fn looks_like_formula(value: &str) -> bool {
+    match value.chars().next() {
+        Some('=' | '@' | '\t' | '\r') => true,
+        Some('+' | '-') => {
+            let rest = &value[1..];
+            !(rest.is_empty() || rest.starts_with(char::is_whitespace) || rest.parse::<f64>().is_ok())
+        }
+        _ => false,
+    }
+}
+
+/// Whether `unescape` takes the `'` off before `value`: it starts with a
+/// character that `cell` may have escaped, possibly behind more `'`.
+fn was_escaped(value: &str) -> bool {
+    value.starts_with(['=', '+', '-', '@', '\t', '\r']) || value.strip_prefix('\'').is_some_and(was_escaped)
+}
+
+/// Prefix formula-like text with `'`, which spreadsheets show as text
+/// rather than run. Text that `unescape` would strip a `'` from is prefixed
+/// too, so every cell reads back as written.
+fn cell(value: String) -> String {
+    if looks_like_formula(&value) || value.strip_prefix('\'').is_some_and(was_escaped) {
+        format!("'{}", value)
+    } else {
+        value
+    }
+}
+
+/// Remove the `'` that `cell` puts before formula-like text.
+fn unescape(value: &str) -> &str {
+    match value.strip_prefix('\'') {
+        Some(rest) if was_escaped(rest) => rest,
+        _ => value,
+    }
+}
+
+fn finish(out: csv::Writer<Vec<u8>>) -> Result<String> {
+    let bytes = out.into_inner().map_err(|e| e.into_error())?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+fn select_columns(
+    spec: Option<&str>,
+    known: &[&'static str],
+    default: &[&'static str],
+) -> Result<Vec<&'static str>> {
+    let spec = match spec {
+        Some(s) if !s.trim().is_empty() => s,
+        _ => return Ok(default.to_vec()),
+    };
+    spec.split(',')
+        .map(|c| {
+            let c = c.trim().to_lowercase();
+            match known.iter().find(|k| **k == c) {
+                Some(k) => Ok(*k),
+                None => bail!("unknown column '{}'; expected {}", c, known.join(", ")),
+            }
+        })
+        .collect()
+}
+
+/// One CSV row, with fields looked up by name through the column mapping.
+struct Row<'a> {
+    record: &'a csv::StringRecord,
+    columns: &'a HashMap<&'static str, usize>,
+}
+
+impl Row<'_> {
+    /// The trimmed value of `field`, or `None` if it is absent or blank.
+    /// The `'` that `cell` puts before formula-like text is removed.
+    fn get(&self, field: &str) -> Option<&str> {
+        let i = *self.columns.get(field)?;
+        let value = self.record.get(i).map(str::trim).filter(|v| !v.is_empty())?;
+        Some(unescape(value))
+    }
+}
+
+/// Work out which CSV column holds each field. `mapping` pairs a field with
+/// the header of the column to read it from (`--map content=Body`); fields
+/// without one use the column whose header is the field name, ignoring case.
+fn resolve_columns(
+    headers: &csv::StringRecord,
+    fields: &[&'static str],
+    mapping: &[(String, String)],
+) -> Result<HashMap<&'static str, usize>> {
+    let find = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case(name.trim()))
+    };
+
+    let mut columns = HashMap::new();
+    for (field, header) in mapping {
+        let field = match fields.iter().find(|f| f.eq_ignore_ascii_case(field.trim())) {
+            Some(f) => *f,
+            None => bail!("unknown field '{}' in --map; expected {}", field, fields.join(", ")),
+        };
+        match find(header) {
+            Some(i) => columns.insert(field, i),
+            None => bail!("no column named '{}' in the CSV header", header),
+        };
+    }
+
+    for field in fields {
+        if columns.contains_key(field) {
+            continue;
+        }
+        if let Some(i) = find(field).filter(|i| !columns.values().any(|used| used == i)) {
+            columns.insert(*field, i);
+        }
+    }
+    Ok(columns)
+}
+
+fn split_tags(s: Option<&str>) -> Vec<String> {
+    s.unwrap_or_default()
+        .split([',', ';'])
+        .map(|t| t.trim().trim_start_matches('#').to_string())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+fn parse_bool(s: &str) -> bool {
+    matches!(
+        s.to_lowercase().as_str(),
+        "true" | "yes" | "y" | "1" | "x"
+    )
+}
+
+/// Load notes from CSV with a header row. The note text comes from a
+/// `content` column, a `title` column, or both (the title becomes the first
+/// line unless the content already starts with it).
+pub fn parse_notes(content: &str, mapping: &[(String, String)]) -> Result<Vec<Note>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let columns = resolve_columns(reader.headers()?, &NOTE_COLUMNS, mapping)?;
+    if !columns.contains_key("content") && !columns.contains_key("title") {
+        bail!("no content or title column; name one with --map content=<header>");
+    }
+
+    let now = Local::now();
+    let mut notes = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        let record = record?;
+        if record.iter().all(|f| f.trim().is_empty()) {
+            continue;
+        }
+        // Header is line 1
+        let line = i + 2;
+        let row = Row {
+            record: &record,
+            columns: &columns,
+        };
+
+        let body = row.get("content").unwrap_or_default();
+        let text = match row.get("title") {
+            Some(title) if note_title(body) != title => {
+                if body.is_empty() {
+                    title.to_string()
+                } else {
+                    format!("# {}\n\n{}", title, body)
+                }
+            }
+            _ => body.to_string(),
+        };
+        if text.is_empty() {
+            bail!("row {}: no content", line);
+        }
+
+        let created_at = match row.get("created") {
+            Some(s) => parse_time(s).with_context(|| format!("row {}", line))?,
+            None => now,
+        };
+        let updated_at = match row.get("updated") {
+            Some(s) => parse_time(s).with_context(|| format!("row {}", line))?,
+            None => created_at,
+        };
+
+        notes.push(Note {
+            id: row.get("id").and_then(|s| s.parse().ok()).unwrap_or(0),
+            content: text,
+            created_at,
+            updated_at,
+            category: row.get("category").map(str::to_string),
+            is_daily: row.get("daily").is_some_and(parse_bool),
+            tags: split_tags(row.get("tags")),
+        });
+    }
+    Ok(notes)
+}
+
+/// Import TODOs from CSV with a header row, skipping tasks that already
+/// exist with the same description and creation date. Only `task` is
+/// required; status defaults to done when a completion date is given.
+pub fn import_todos(conn: &Connection, content: &str, mapping: &[(String, String)]) -> Result<ImportReport> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let columns = resolve_columns(reader.headers()?, &TODO_IMPORT_FIELDS, mapping)?;
+    if !columns.contains_key("task") {
+        bail!("no task column; name one with --map task=<header>");
+    }
+
+    let mut parsed = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        let record = record?;
+        if record.iter().all(|f| f.trim().is_empty()) {
+            continue;
+        }
+        let line = i + 2;
+        let row = Row {
+            record: &record,
+            columns: &columns,
+        };
+        parsed.push(parse_todo(&row).with_context(|| format!("row {}", line))?);
+    }
+
+    Ok(todo::import_deduped(conn, &parsed, false)?)
+}
+
+fn parse_todo(row: &Row) -> Result<Todo> {
+    let task = match row.get("task") {
+        Some(t) => t.to_string(),
+        None => bail!("no task"),
+    };
+
+    let date = |field: &str| -> Result<Option<NaiveDate>> {
+        row.get(field)
+            .map(|s| parse_time(s).map(|dt| dt.date_naive()))
+            .transpose()
+    };
+    let completed_at = row.get("completed").map(parse_time).transpose()?;
+
+    let status = match row.get("status").map(str::to_lowercase) {
+        Some(s) if STATUSES.contains(&s.as_str()) => s,
+        Some(s) => bail!("unknown status '{}'; expected {}", s, STATUSES.join(", ")),
+        None if completed_at.is_some() => "done".to_string(),
+        None => "todo".to_string(),
+    };
+    let completed = is_closed_status(&status);
+
+    let priority = match row.get("priority").map(str::to_lowercase).as_deref() {
+        None => "medium",
+        Some("high" | "h" | "a") => "high",
+        Some("medium" | "med" | "m" | "b") => "medium",
+        Some("low" | "l" | "c") => "low",
+        Some(p) => bail!("unknown priority '{}'; expected high, medium or low", p),
+    };
+
+    let created_at = row.get("created").map(parse_time).transpose()?;
+
+    Ok(Todo {
+        id: 0,
+        task,
+        completed,
+        status,
+        priority: priority.to_string(),
+        due_date: date("due")?.map(|d| timestamp_to_local(day_bounds(d).1)),
+        created_at: created_at.unwrap_or_else(Local::now),
+        project: row.get("project").map(str::to_string),
+        tags: split_tags(row.get("tags")),
+        note_id: None,
+        blocked_by: Vec::new(),
+        tracked_secs: 0,
+        defer_until: date("start")?.map(|d| timestamp_to_local(day_bounds(d).0)),
+        completed_at: if completed {
+            Some(completed_at.unwrap_or_else(Local::now))
+        } else {
+            None
+        },
+        archived: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_formula_like_cells_are_escaped() {
+        for plain in ["- [ ] task", "- item", "-12.5", "+1", "-", "plain", "'quoted"] {
+            assert_eq!(cell(plain.to_string()), plain);
+        }
+        for formula in ["=SUM(A1:A2)", "@cmd", "+A1", "-1+2", "-cmd|' /C calc'!A0", "'=1"] {
+            assert_eq!(cell(formula.to_string()), format!("'{}", formula));
+        }
+    }
+
+    #[test]
+    fn cells_read_back_as_written() {
+        for value in ["- [ ] task", "=1", "''=1", "'- item", "-12.5", "@x", "it's"] {
+            assert_eq!(unescape(&cell(value.to_string())), value);
+        }
+        // Exports that escaped list items still import without the `'`
+        assert_eq!(unescape("'- item"), "- item");
+    }
+}
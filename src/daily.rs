@@ -0,0 +1,121 @@
+use chrono::{Local, NaiveDate};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqlResult};
+
+use crate::note::{self, Note};
+use crate::todo;
+use crate::utils::day_bounds;
+
+/// Marker for items moved to a later daily note, as in a bullet journal.
+/// It is not a checkbox, so the item no longer syncs from the old note.
+const MIGRATED: &str = "[>]";
+
+/// The daily note for `date`, if one exists.
+pub fn find(conn: &Connection, date: NaiveDate) -> SqlResult<Option<Note>> {
+    Ok(note::list_daily(conn, date, date)?.into_iter().next())
+}
+
+/// The most recent daily note from before `date`.
+pub fn previous(conn: &Connection, date: NaiveDate) -> SqlResult<Option<Note>> {
+    let (start, _) = day_bounds(date);
+    let id: Option<i64> = conn
+        .query_row(
+            "SELECT id FROM notes WHERE is_daily = 1 AND created_at < ?1 \
+             ORDER BY created_at DESC LIMIT 1",
+            params![start],
+            |row| row.get(0),
+        )
+        .optional()?;
+    match id {
+        Some(id) => note::get_by_id(conn, id),
+        None => Ok(None),
+    }
+}
+
+/// Unchecked checkbox items in a note, in order.
+pub fn open_items(note: &Note) -> Vec<String> {
+    todo::extract_checkboxes(&note.content)
+        .into_iter()
+        .filter(|c| !c.checked)
+        .map(|c| c.task)
+        .collect()
+}
+
+/// Create the daily note for `date`. Notes for today are stamped with the
+/// current time; notes for other days with the start of that day, so
+/// `find` locates them again.
+pub fn create(
+    conn: &Connection,
+    date: NaiveDate,
+    content: &str,
+    tags: &[String],
+    category: Option<&str>,
+) -> SqlResult<i64> {
+    let created_at = if date == Local::now().date_naive() {
+        Local::now().timestamp()
+    } else {
+        day_bounds(date).0
+    };
+    note::add_at(conn, content, tags, category, true, created_at)
+}
+
+/// Finish carrying unchecked items from the `from` note into the new daily
+/// note `to_id`. Items still present in the new note keep their original
+/// TODO (and its tracked time), relinked to the new note, and are marked
+/// `[>]` in the old one. Returns how many items were carried.
+pub fn carry_over(conn: &Connection, from: &Note, to_id: i64) -> SqlResult<usize> {
+    let to = match note::get_by_id(conn, to_id)? {
+        Some(n) => n,
+        None => return Ok(0),
+    };
+    let present = todo::extract_checkboxes(&to.content);
+    let carried: Vec<String> = open_items(from)
+        .into_iter()
+        .filter(|task| present.iter().any(|c| c.task == *task))
+        .collect();
+    if carried.is_empty() {
+        return Ok(0);
+    }
+
+    for task in &carried {
+        let old: Option<i64> = conn
+            .query_row(
+                "SELECT id FROM todos WHERE note_id = ?1 AND task = ?2 AND completed = 0 LIMIT 1",
+                params![from.id, task],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let new: Option<(i64, bool)> = conn
+            .query_row(
+                "SELECT id, completed FROM todos WHERE note_id = ?1 AND task = ?2 LIMIT 1",
+                params![to_id, task],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+
+        if let (Some(old), Some((new, completed))) = (old, new) {
+            todo::delete(conn, new)?;
+            conn.execute(
+                "UPDATE todos SET note_id = ?1 WHERE id = ?2",
+                params![to_id, old],
+            )?;
+            if completed {
+                todo::set_status(conn, old, "done")?;
+            }
+        }
+    }
+
+    let lines: Vec<String> = from
+        .content
+        .lines()
+        .map(|line| match todo::parse_checkbox(line) {
+            Some(c) if !c.checked && carried.contains(&c.task) => {
+                let pos = line.find('[').unwrap_or(0);
+                format!("{}{}{}", &line[..pos], MIGRATED, &line[pos + 3..])
+            }
+            _ => line.to_string(),
+        })
+        .collect();
+    note::update(conn, from.id, &lines.join("\n"))?;
+
+    Ok(carried.len())
+}
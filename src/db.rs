@@ -1,5 +1,7 @@
-use rusqlite::{Connection, Result as SqlResult};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqlResult};
+use std::collections::hash_map::RandomState;
 use std::fs;
+use std::hash::{BuildHasher, Hasher};
 use std::path::PathBuf;
 
 pub fn get_db_dir() -> PathBuf {
@@ -12,6 +14,11 @@ pub fn get_db_path() -> PathBuf {
     get_db_dir().join("notes.db")
 }
 
+/// Version of the schema `initialize` produces, stored in `PRAGMA
+/// user_version`. Bump it whenever `initialize` changes the schema, so older
+/// databases are backed up before they are migrated.
+pub const SCHEMA_VERSION: i64 = 3;
+
 pub fn open_connection() -> SqlResult<Connection> {
     let db_dir = get_db_dir();
     fs::create_dir_all(&db_dir).expect("Could not create ~/.notectl directory");
@@ -21,6 +28,8 @@ pub fn open_connection() -> SqlResult<Connection> {
 }
 
 pub fn initialize(conn: &Connection) -> SqlResult<()> {
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
     conn.execute_batch(
         "
         CREATE TABLE IF NOT EXISTS notes (
@@ -50,13 +59,63 @@ pub fn initialize(conn: &Connection) -> SqlResult<()> {
             created_at INTEGER NOT NULL
         );
 
+        CREATE TABLE IF NOT EXISTS todo_tags (
+            todo_id INTEGER NOT NULL,
+            tag TEXT NOT NULL,
+            FOREIGN KEY (todo_id) REFERENCES todos(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_todo_tags_todo_id ON todo_tags(todo_id);
+        CREATE INDEX IF NOT EXISTS idx_todo_tags_tag ON todo_tags(tag);
+
+        CREATE TABLE IF NOT EXISTS todo_deps (
+            todo_id INTEGER NOT NULL,
+            blocked_by INTEGER NOT NULL,
+            PRIMARY KEY (todo_id, blocked_by),
+            FOREIGN KEY (todo_id) REFERENCES todos(id) ON DELETE CASCADE,
+            FOREIGN KEY (blocked_by) REFERENCES todos(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS time_entries (
+            id INTEGER PRIMARY KEY,
+            todo_id INTEGER NOT NULL,
+            started_at INTEGER NOT NULL,
+            ended_at INTEGER,
+            FOREIGN KEY (todo_id) REFERENCES todos(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_time_entries_todo_id ON time_entries(todo_id);
+        -- At most one running timer across all TODOs
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_time_entries_running
+            ON time_entries((ended_at IS NULL)) WHERE ended_at IS NULL;
+
         CREATE TABLE IF NOT EXISTS templates (
             name TEXT PRIMARY KEY,
             content TEXT NOT NULL
         );
+
+        -- Values that belong to this database as a whole
+        CREATE TABLE IF NOT EXISTS meta (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
         ",
     )?;
 
+    // Columns added after the initial schema. CREATE TABLE IF NOT EXISTS
+    // leaves existing tables untouched, so older databases need an ALTER.
+    add_column_if_missing(conn, "todos", "project", "TEXT")?;
+    add_column_if_missing(conn, "todos", "defer_until", "INTEGER")?;
+    add_column_if_missing(conn, "todos", "completed_at", "INTEGER")?;
+    add_column_if_missing(conn, "todos", "archived", "BOOLEAN NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "todos", "note_id", "INTEGER REFERENCES notes(id) ON DELETE SET NULL")?;
+    conn.execute_batch("CREATE INDEX IF NOT EXISTS idx_todos_note_id ON todos(note_id);")?;
+    if add_column_if_missing(conn, "todos", "status", "TEXT NOT NULL DEFAULT 'todo'")? {
+        conn.execute_batch("UPDATE todos SET status = 'done' WHERE completed = 1;")?;
+    }
+    // 'weekly' or 'monthly' for periodic review notes
+    add_column_if_missing(conn, "notes", "period", "TEXT")?;
+
     // Create FTS table if it doesn't exist
     // We use a separate check because CREATE VIRTUAL TABLE IF NOT EXISTS
     // doesn't work reliably across all SQLite versions
@@ -72,5 +131,77 @@ pub fn initialize(conn: &Connection) -> SqlResult<()> {
         )?;
     }
 
+    // Version 2 links checkboxes to TODOs; notes written before then get
+    // theirs once
+    if version < 2 {
+        let notes: Vec<(i64, String)> = {
+            let mut stmt = conn.prepare("SELECT id, content FROM notes")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<SqlResult<_>>()?
+        };
+        for (id, content) in notes {
+            crate::todo::sync_from_note(conn, id, &content)?;
+        }
+    }
+
+    conn.execute_batch(&format!("PRAGMA user_version = {};", SCHEMA_VERSION))?;
+
     Ok(())
 }
+
+/// A random id for this database, created the first time it is asked for.
+/// Exports mix it into identifiers that must not collide with those from
+/// another notectl database, such as iCalendar UIDs.
+pub fn instance_id(conn: &Connection) -> SqlResult<String> {
+    let stored = |conn: &Connection| {
+        conn.query_row("SELECT value FROM meta WHERE key = 'instance_id'", [], |row| row.get(0))
+            .optional()
+    };
+    if let Some(id) = stored(conn)? {
+        return Ok(id);
+    }
+
+    // RandomState is seeded from the OS, which is all the randomness needed
+    let random = || RandomState::new().build_hasher().finish();
+    let id = format!("{:016x}{:016x}", random(), random());
+    conn.execute(
+        "INSERT OR IGNORE INTO meta (key, value) VALUES ('instance_id', ?1)",
+        params![id],
+    )?;
+    Ok(stored(conn)?.unwrap_or(id))
+}
+
+/// Whether `initialize` is about to migrate an existing database, as
+/// opposed to creating a fresh one or finding it up to date.
+pub fn needs_migration(conn: &Connection) -> SqlResult<bool> {
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    let has_notes: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'notes'",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(has_notes && version < SCHEMA_VERSION)
+}
+
+/// Add a column to an existing table, returning whether it was missing.
+fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> SqlResult<bool> {
+    let exists: bool = conn.query_row(
+        &format!("SELECT COUNT(*) > 0 FROM pragma_table_info('{}') WHERE name = ?1", table),
+        [column],
+        |row| row.get(0),
+    )?;
+
+    if !exists {
+        conn.execute_batch(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {};",
+            table, column, definition
+        ))?;
+    }
+
+    Ok(!exists)
+}
@@ -1,13 +1,17 @@
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Datelike, Duration, Local};
 use colored::*;
+use std::collections::HashMap;
 use tabled::{
     settings::{Style, Modify, object::Columns, Width},
     Table, Tabled,
 };
 
+use crate::agenda::Agenda;
 use crate::note::Note;
 use crate::tags::TagCount;
-use crate::todo::Todo;
+use crate::timelog::{ReportRow, RunningTimer};
+use crate::todo::{ProjectProgress, Todo};
+use crate::utils::format_duration;
 
 #[derive(Tabled)]
 struct NoteRow {
@@ -27,10 +31,16 @@ struct TodoRow {
     id: i64,
     #[tabled(rename = "Task")]
     task: String,
+    #[tabled(rename = "Project")]
+    project: String,
+    #[tabled(rename = "Tags")]
+    tags: String,
     #[tabled(rename = "Priority")]
     priority: String,
     #[tabled(rename = "Due")]
     due: String,
+    #[tabled(rename = "Time")]
+    time: String,
     #[tabled(rename = "Status")]
     status: String,
 }
@@ -114,10 +124,9 @@ pub fn print_search_results(notes: &[Note], query: &str, full: bool) {
 
     for note in notes {
         println!(
-            "{} {} {}",
+            "{} {}",
             format!("[{}]", note.id).cyan(),
-            note.created_at.format("%Y-%m-%d %H:%M").to_string().dimmed(),
-            ""
+            note.created_at.format("%Y-%m-%d %H:%M").to_string().dimmed()
         );
         if full {
             println!("  {}", note.content);
@@ -131,13 +140,13 @@ pub fn print_search_results(notes: &[Note], query: &str, full: bool) {
     }
 }
 
-pub fn print_todos_table(todos: &[Todo]) {
+pub fn print_todos_table(todos: &[Todo], title: &str) {
     if todos.is_empty() {
         println!("{}", "No TODOs found.".dimmed());
         return;
     }
 
-    println!("{}\n", "Active TODOs:".bold());
+    println!("{}:\n", title.bold());
 
     let rows: Vec<TodoRow> = todos
         .iter()
@@ -163,17 +172,28 @@ pub fn print_todos_table(todos: &[Todo]) {
                 None => "-".dimmed().to_string(),
             };
 
-            let status = if t.completed {
-                "Done".green().to_string()
+            let status = if t.is_deferred() {
+                let until = t.defer_until.map(|d| d.format("%b %-d").to_string()).unwrap_or_default();
+                format!("Deferred ({})", until).dimmed().to_string()
+            } else if t.is_blocked() {
+                let ids: Vec<String> = t.blocked_by.iter().map(|b| format!("#{}", b)).collect();
+                format!("Blocked ({})", ids.join(", ")).magenta().to_string()
             } else {
-                "Pending".dimmed().to_string()
+                status_label(&t.status)
             };
 
             TodoRow {
                 id: t.id,
                 task: truncate(&t.task, 35),
+                project: t.project.clone().unwrap_or_else(|| "-".dimmed().to_string()),
+                tags: t.tags.join(", "),
                 priority: priority_display,
                 due: due_display,
+                time: if t.tracked_secs > 0 {
+                    format_duration(t.tracked_secs)
+                } else {
+                    "-".dimmed().to_string()
+                },
                 status,
             }
         })
@@ -186,6 +206,90 @@ pub fn print_todos_table(todos: &[Todo]) {
     println!("{}", table);
 }
 
+fn status_label(status: &str) -> String {
+    match status {
+        "todo" => "Pending".dimmed().to_string(),
+        "doing" => "Doing".blue().to_string(),
+        "waiting" => "Waiting".yellow().to_string(),
+        "done" => "Done".green().to_string(),
+        "cancelled" => "Cancelled".dimmed().strikethrough().to_string(),
+        other => {
+            let mut chars = other.chars();
+            match chars.next() {
+                Some(c) => c.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        }
+    }
+}
+
+/// Render workflow columns side by side, flagging columns over their WIP limit.
+pub fn print_board(columns: &[(String, Vec<Todo>)], wip_limits: &HashMap<String, usize>) {
+    if columns.is_empty() {
+        println!("{}", "No board columns configured.".dimmed());
+        return;
+    }
+
+    let mut builder = tabled::builder::Builder::default();
+
+    let headers: Vec<String> = columns
+        .iter()
+        .map(|(status, todos)| {
+            let label = status_label(status);
+            match wip_limits.get(status) {
+                Some(&limit) if todos.len() > limit => {
+                    format!("{} {}", label, format!("({}/{})", todos.len(), limit).red().bold())
+                }
+                Some(&limit) => format!("{} ({}/{})", label, todos.len(), limit),
+                None => format!("{} ({})", label, todos.len()),
+            }
+        })
+        .collect();
+    builder.push_record(headers);
+
+    let depth = columns.iter().map(|(_, t)| t.len()).max().unwrap_or(0);
+    for i in 0..depth {
+        let record: Vec<String> = columns
+            .iter()
+            .map(|(_, todos)| match todos.get(i) {
+                Some(t) => {
+                    let id = match t.priority.as_str() {
+                        "high" => format!("#{}", t.id).red().to_string(),
+                        "low" => format!("#{}", t.id).green().to_string(),
+                        _ => format!("#{}", t.id).cyan().to_string(),
+                    };
+                    let blocked = if t.is_blocked() {
+                        " [blocked]".magenta().to_string()
+                    } else {
+                        String::new()
+                    };
+                    format!("{} {}{}", id, truncate(&t.task, 22), blocked)
+                }
+                None => String::new(),
+            })
+            .collect();
+        builder.push_record(record);
+    }
+
+    println!("{}", builder.build().with(Style::rounded()));
+}
+
+pub fn print_linked_todos(todos: &[Todo]) {
+    if todos.is_empty() {
+        return;
+    }
+
+    println!("\n{}", "Linked TODOs:".bold());
+    for t in todos {
+        let mark = if t.completed {
+            "[x]".green().to_string()
+        } else {
+            "[ ]".dimmed().to_string()
+        };
+        println!("  {} {} {}", mark, format!("#{}", t.id).cyan(), t.task);
+    }
+}
+
 pub fn print_todo_summary(overdue: i64, due_today: i64) {
     println!(
         "\nOverdue: {} | Due today: {}",
@@ -202,6 +306,175 @@ pub fn print_todo_summary(overdue: i64, due_today: i64) {
     );
 }
 
+pub fn print_project_progress(projects: &[ProjectProgress]) {
+    let width = projects.iter().map(|p| p.project.chars().count()).max().unwrap_or(0);
+
+    for p in projects {
+        let pct = if p.total > 0 { p.completed * 100 / p.total } else { 0 };
+        let filled = (pct / 10) as usize;
+        let bar = format!("{}{}", "█".repeat(filled), "░".repeat(10 - filled));
+        println!(
+            "  {}  {} {}/{} ({}%)",
+            format!("{:<width$}", p.project, width = width).cyan(),
+            if pct == 100 { bar.green() } else { bar.normal() },
+            p.completed,
+            p.total,
+            pct
+        );
+    }
+}
+
+pub fn print_agenda(agenda: &Agenda) {
+    let (first, last) = match (agenda.days.first(), agenda.days.last()) {
+        (Some(f), Some(l)) => (f.date, l.date),
+        _ => return,
+    };
+    println!(
+        "{} ({} - {}):",
+        "Agenda".bold(),
+        first.format("%b %-d"),
+        last.format("%b %-d")
+    );
+
+    if !agenda.overdue.is_empty() {
+        println!("\n{}", "Overdue".red().bold());
+        for t in &agenda.overdue {
+            print_agenda_todo(t, true);
+        }
+    }
+
+    for day in &agenda.days {
+        let offset = (day.date - agenda.today).num_days();
+        if offset > 1 && day.todos.is_empty() && day.daily_note.is_none() {
+            continue;
+        }
+
+        let label = match offset {
+            0 => format!("Today · {}", day.date.format("%a %b %-d")),
+            1 => format!("Tomorrow · {}", day.date.format("%a %b %-d")),
+            _ => day.date.format("%a %b %-d").to_string(),
+        };
+        println!("\n{}", label.bold());
+
+        if let Some(ref n) = day.daily_note {
+            println!(
+                "  {} {}",
+                format!("Daily note #{}", n.id).cyan(),
+                truncate(n.content.lines().next().unwrap_or(""), 50).dimmed()
+            );
+        }
+        for t in &day.todos {
+            print_agenda_todo(t, false);
+        }
+        if day.todos.is_empty() && day.daily_note.is_none() {
+            println!("  {}", "Nothing scheduled".dimmed());
+        }
+    }
+}
+
+fn print_agenda_todo(t: &Todo, show_date: bool) {
+    let priority = match t.priority.as_str() {
+        "high" => "High".red().to_string(),
+        "low" => "Low".green().to_string(),
+        _ => "Med".yellow().to_string(),
+    };
+    let date = match (&t.due_date, show_date) {
+        (Some(dt), true) => format!(" ({})", dt.format("%b %-d")).red().to_string(),
+        _ => String::new(),
+    };
+    let blocked = if t.is_blocked() {
+        " [blocked]".magenta().to_string()
+    } else {
+        String::new()
+    };
+    println!(
+        "  {} [{}] {}{}{}",
+        format!("#{}", t.id).cyan(),
+        priority,
+        truncate(&t.task, 50),
+        blocked,
+        date
+    );
+}
+
+/// Render the agenda range as a Monday-first week grid with due counts.
+pub fn print_agenda_calendar(agenda: &Agenda) {
+    let (first, last) = match (agenda.days.first(), agenda.days.last()) {
+        (Some(f), Some(l)) => (f.date, l.date),
+        _ => return,
+    };
+
+    let mut builder = tabled::builder::Builder::default();
+    builder.push_record(["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"]);
+
+    let mut week_start = first - Duration::days(i64::from(first.weekday().num_days_from_monday()));
+    while week_start <= last {
+        let mut record = Vec::with_capacity(7);
+        for i in 0..7 {
+            let date = week_start + Duration::days(i);
+            let mut cell = date.format("%-d").to_string();
+            if date == agenda.today {
+                cell = cell.reversed().to_string();
+            }
+            match agenda.days.iter().find(|d| d.date == date) {
+                Some(day) => {
+                    let overdue = if date == agenda.today { agenda.overdue.len() } else { 0 };
+                    if overdue > 0 {
+                        cell.push_str(&format!("\n{}", format!("{} late", overdue).red()));
+                    }
+                    if !day.todos.is_empty() {
+                        cell.push_str(&format!("\n{}", format!("{} due", day.todos.len()).yellow()));
+                    }
+                    if day.daily_note.is_some() {
+                        cell.push_str(&format!("\n{}", "note".cyan()));
+                    }
+                }
+                None => cell = cell.dimmed().to_string(),
+            }
+            record.push(cell);
+        }
+        builder.push_record(record);
+        week_start += Duration::days(7);
+    }
+
+    println!("{}", builder.build().with(Style::rounded()));
+}
+
+pub fn print_time_report(
+    rows: &[ReportRow],
+    total: i64,
+    title: &str,
+    running: Option<&RunningTimer>,
+) {
+    if rows.is_empty() {
+        println!("{}", "No time tracked in this period.".dimmed());
+    } else {
+        println!("{}:\n", title.bold());
+
+        let width = rows.iter().map(|r| r.key.chars().count()).max().unwrap_or(0);
+        for r in rows {
+            println!(
+                "  {}  {:>8}",
+                format!("{:<width$}", truncate(&r.key, 50), width = width.min(53)).cyan(),
+                format_duration(r.seconds)
+            );
+        }
+
+        println!("\n  Total: {}", format_duration(total).bold());
+    }
+
+    if let Some(t) = running {
+        let elapsed = Local::now().timestamp() - t.started_at;
+        println!(
+            "  {} #{} {} ({})",
+            "Running:".yellow(),
+            t.todo_id,
+            truncate(&t.task, 40),
+            format_duration(elapsed)
+        );
+    }
+}
+
 pub fn print_tags_table(tags: &[TagCount]) {
     if tags.is_empty() {
         println!("{}", "No tags found.".dimmed());
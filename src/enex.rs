@@ -0,0 +1,796 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, NaiveDateTime, Utc};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+
+use crate::note::Note;
+
+/// An embedded file to be written next to an imported note.
+pub struct Attachment {
+    pub path: PathBuf,
+    pub data: Vec<u8>,
+}
+
+/// Notes read from an Evernote export, the attachments each one links to
+/// (`attachments[i]` belongs to `notes[i]`), and the parts that were left
+/// out, as `(note title, what and why)`.
+pub struct EnexScan {
+    pub notes: Vec<Note>,
+    pub attachments: Vec<Vec<Attachment>>,
+    pub skipped: Vec<(String, String)>,
+}
+
+#[derive(Default)]
+struct RawNote {
+    title: String,
+    content: String,
+    created: String,
+    updated: String,
+    tags: Vec<String>,
+    resources: Vec<RawResource>,
+}
+
+#[derive(Default)]
+struct RawResource {
+    data: String,
+    mime: String,
+    file_name: String,
+}
+
+/// Parse an `.enex` export as it is read. Note bodies (ENML) are converted
+/// to Markdown. Embedded resources are linked from the note and returned
+/// with it as attachments under `attachments_dir`, or skipped and reported
+/// when it is `None`. Nothing is written to disk.
+pub fn parse<R: BufRead>(input: R, attachments_dir: Option<&Path>) -> Result<EnexScan> {
+    let mut reader = Reader::from_reader(input);
+    reader.config_mut().trim_text(false);
+
+    let mut scan = EnexScan {
+        notes: Vec::new(),
+        attachments: Vec::new(),
+        skipped: Vec::new(),
+    };
+    let mut path: Vec<String> = Vec::new();
+    let mut current: Option<RawNote> = None;
+    let mut saw_export = false;
+    let mut buf = Vec::new();
+
+    loop {
+        buf.clear();
+        let event = reader
+            .read_event_into(&mut buf)
+            .with_context(|| format!("invalid XML at byte {}", reader.buffer_position()))?;
+        match event {
+            Event::Start(e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).to_string();
+                match name.as_str() {
+                    "en-export" => saw_export = true,
+                    "note" => current = Some(RawNote::default()),
+                    "resource" => {
+                        if let Some(ref mut n) = current {
+                            n.resources.push(RawResource::default());
+                        }
+                    }
+                    _ => {}
+                }
+                path.push(name);
+            }
+            Event::End(e) => {
+                path.pop();
+                if e.local_name().as_ref() == b"note" {
+                    if let Some(raw) = current.take() {
+                        convert_note(raw, attachments_dir, &mut scan);
+                    }
+                }
+            }
+            Event::Text(e) => {
+                let text = e.unescape().unwrap_or_default();
+                append_field(&mut current, &path, &text);
+            }
+            Event::CData(e) => {
+                let text = String::from_utf8_lossy(&e).to_string();
+                append_field(&mut current, &path, &text);
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if !saw_export {
+        bail!("not an Evernote export (no <en-export> element)");
+    }
+    Ok(scan)
+}
+
+/// Add text to the note field named by the innermost open element.
+fn append_field(current: &mut Option<RawNote>, path: &[String], text: &str) {
+    let Some(note) = current.as_mut() else {
+        return;
+    };
+    let Some(field) = path.last() else {
+        return;
+    };
+    let in_resource = path.iter().any(|p| p == "resource");
+
+    if in_resource {
+        let Some(res) = note.resources.last_mut() else {
+            return;
+        };
+        match field.as_str() {
+            "data" => res.data.push_str(text),
+            "mime" => res.mime.push_str(text.trim()),
+            "file-name" => res.file_name.push_str(text.trim()),
+            _ => {}
+        }
+        return;
+    }
+
+    match field.as_str() {
+        "title" => note.title.push_str(text),
+        "content" => note.content.push_str(text),
+        "created" => note.created.push_str(text.trim()),
+        "updated" => note.updated.push_str(text.trim()),
+        "tag" => {
+            let tag = text.trim();
+            if !tag.is_empty() {
+                note.tags.push(tag.to_string());
+            }
+        }
+        _ => {}
+    }
+}
+
+/// `20260214T093000Z`, the ENEX timestamp format, in UTC.
+fn parse_enex_time(s: &str) -> Option<DateTime<Local>> {
+    NaiveDateTime::parse_from_str(s, "%Y%m%dT%H%M%SZ")
+        .ok()
+        .map(|dt| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc).with_timezone(&Local))
+}
+
+fn convert_note(raw: RawNote, attachments_dir: Option<&Path>, scan: &mut EnexScan) {
+    let title = raw.title.trim().to_string();
+    let label = if title.is_empty() {
+        "(untitled)".to_string()
+    } else {
+        title.clone()
+    };
+
+    // en-media elements refer to resources by the MD5 of their data
+    let mut media: HashMap<String, String> = HashMap::new();
+    let mut order: Vec<String> = Vec::new();
+    let mut attachments = Vec::new();
+    for res in raw.resources {
+        let name = attachment_name(&res);
+        let data = match decode_base64(&res.data) {
+            Some(d) => d,
+            None => {
+                scan.skipped.push((label.clone(), format!("{} (invalid base64 data)", name)));
+                continue;
+            }
+        };
+        let dir = match attachments_dir {
+            Some(d) => d,
+            None => {
+                scan.skipped.push((label.clone(), format!("{} (attachments skipped)", name)));
+                continue;
+            }
+        };
+
+        let hash = format!("{:x}", md5::compute(&data));
+        let path = dir.join(&hash).join(&name);
+        let target = path.display().to_string();
+        let target = if target.contains(|c: char| c.is_whitespace() || c == '(' || c == ')') {
+            format!("<{}>", target)
+        } else {
+            target
+        };
+        let link = if res.mime.starts_with("image/") {
+            format!("![{}]({})", name, target)
+        } else {
+            format!("[{}]({})", name, target)
+        };
+
+        if let Entry::Vacant(slot) = media.entry(hash.clone()) {
+            slot.insert(link);
+            order.push(hash);
+            attachments.push(Attachment { path, data });
+        }
+    }
+
+    let mut converter = Converter::new(&media);
+    converter.run(&raw.content);
+    if let Some(pos) = converter.error {
+        scan.skipped.push((
+            label.clone(),
+            format!("invalid ENML, rest of the body from byte {} left out", pos),
+        ));
+    }
+    if converter.encrypted > 0 {
+        scan.skipped.push((
+            label.clone(),
+            format!("{} encrypted section(s)", converter.encrypted),
+        ));
+    }
+
+    let mut body = converter.finish();
+    let unreferenced: Vec<&str> = order
+        .iter()
+        .filter(|h| !converter.used.contains(*h))
+        .map(|h| media[h].as_str())
+        .collect();
+    if !unreferenced.is_empty() {
+        if !body.is_empty() {
+            body.push_str("\n\n");
+        }
+        body.push_str(&unreferenced.join("\n"));
+    }
+
+    let content = match (title.is_empty(), body.is_empty()) {
+        (true, true) => {
+            scan.skipped.push((label, "empty note".to_string()));
+            return;
+        }
+        (true, false) => body,
+        (false, true) => format!("# {}", title),
+        (false, false) => format!("# {}\n\n{}", title, body),
+    };
+
+    let created_at = parse_enex_time(&raw.created).unwrap_or_else(Local::now);
+    let updated_at = parse_enex_time(&raw.updated).unwrap_or(created_at);
+
+    scan.notes.push(Note {
+        id: 0,
+        content,
+        created_at,
+        updated_at,
+        category: None,
+        is_daily: false,
+        tags: raw.tags,
+    });
+    scan.attachments.push(attachments);
+}
+
+/// The resource's own file name without any directories, or one made up
+/// from its MIME type.
+fn attachment_name(res: &RawResource) -> String {
+    let given = res
+        .file_name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or("")
+        .trim()
+        .trim_start_matches('.');
+    if !given.is_empty() {
+        return given.to_string();
+    }
+    let ext = match res.mime.split('/').nth(1) {
+        Some("jpeg") => "jpg",
+        Some("svg+xml") => "svg",
+        Some(sub) if !sub.is_empty() && sub.chars().all(|c| c.is_ascii_alphanumeric()) => sub,
+        _ => "bin",
+    };
+    format!("attachment.{}", ext)
+}
+
+/// Decode standard or URL-safe base64, ignoring whitespace. Padding is
+/// optional, but nothing but padding may follow it.
+fn decode_base64(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len() * 3 / 4);
+    let mut buf: u32 = 0;
+    let mut bits = 0;
+    let mut padded = false;
+    for c in s.bytes() {
+        let v = match c {
+            c if c.is_ascii_whitespace() => continue,
+            b'=' => {
+                padded = true;
+                continue;
+            }
+            _ if padded => return None,
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return None,
+        };
+        buf = (buf << 6) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buf >> bits) as u8);
+            buf &= (1 << bits) - 1;
+        }
+    }
+    // A single character left over can't encode a whole byte
+    if bits >= 6 {
+        return None;
+    }
+    Some(out)
+}
+
+/// Write attachments that aren't on disk yet, or with `dry_run` only count
+/// them. Returns how many were (or would be) written.
+pub fn save_attachments<'a>(
+    attachments: impl IntoIterator<Item = &'a Attachment>,
+    dry_run: bool,
+) -> Result<usize> {
+    let mut written: HashSet<&Path> = HashSet::new();
+    for a in attachments {
+        if a.path.exists() || !written.insert(&a.path) || dry_run {
+            continue;
+        }
+        if let Some(parent) = a.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&a.path, &a.data).with_context(|| format!("cannot write {}", a.path.display()))?;
+    }
+    Ok(written.len())
+}
+
+fn is_name_start(name: &[u8]) -> bool {
+    name.first()
+        .is_some_and(|&b| b.is_ascii_alphabetic() || b == b'_' || b == b':' || b >= 0x80)
+}
+
+/// HTML entities ENML borrows from XHTML, beyond the five XML defines.
+fn html_entity(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "amp" => "&",
+        "lt" => "<",
+        "gt" => ">",
+        "quot" => "\"",
+        "apos" => "'",
+        "nbsp" => " ",
+        "ndash" => "–",
+        "mdash" => "—",
+        "hellip" => "…",
+        "lsquo" => "‘",
+        "rsquo" => "’",
+        "ldquo" => "“",
+        "rdquo" => "”",
+        "laquo" => "«",
+        "raquo" => "»",
+        "bull" => "•",
+        "middot" => "·",
+        "times" => "×",
+        "copy" => "©",
+        "reg" => "®",
+        "trade" => "™",
+        "euro" => "€",
+        "deg" => "°",
+        _ => return None,
+    })
+}
+
+/// What to emit when an element closes.
+enum Closer {
+    Nothing,
+    Block,
+    Paragraph,
+    Inline(&'static str),
+    Link(String),
+    Pre,
+    List,
+    Item,
+    Quote(usize),
+    Table,
+    Row,
+    Cell,
+    Crypt,
+}
+
+/// Streams ENML into Markdown.
+struct Converter<'a> {
+    out: String,
+    /// Whether the current line has text, as opposed to nothing or only a
+    /// list marker
+    line_has_text: bool,
+    closers: Vec<Closer>,
+    /// Open lists: `None` for bullets, the next number for ordered ones
+    lists: Vec<Option<usize>>,
+    pre: usize,
+    cells: usize,
+    /// Rows written and cells in the current row, per open table
+    tables: Vec<(usize, usize)>,
+    crypt: usize,
+    media: &'a HashMap<String, String>,
+    used: HashSet<String>,
+    encrypted: usize,
+    /// Byte offset where the body stopped parsing, if it did
+    error: Option<u64>,
+}
+
+impl<'a> Converter<'a> {
+    fn new(media: &'a HashMap<String, String>) -> Self {
+        Converter {
+            out: String::new(),
+            line_has_text: false,
+            closers: Vec::new(),
+            lists: Vec::new(),
+            pre: 0,
+            cells: 0,
+            tables: Vec::new(),
+            crypt: 0,
+            media,
+            used: HashSet::new(),
+            encrypted: 0,
+            error: None,
+        }
+    }
+
+    fn run(&mut self, enml: &str) {
+        let mut reader = Reader::from_str(enml);
+        let config = reader.config_mut();
+        config.trim_text(false);
+        config.check_end_names = false;
+
+        loop {
+            let pos = reader.buffer_position();
+            match reader.read_event() {
+                // A stray `<`, as in `a <= b`, reads as a tag with no name
+                Ok(Event::Start(e) | Event::Empty(e)) if !is_name_start(e.name().as_ref()) => {
+                    self.error = Some(pos);
+                    break;
+                }
+                Ok(Event::Start(e)) => {
+                    let closer = self.open(&e);
+                    self.closers.push(closer);
+                }
+                Ok(Event::Empty(e)) => {
+                    let closer = self.open(&e);
+                    self.close(closer);
+                }
+                Ok(Event::End(_)) => {
+                    if let Some(closer) = self.closers.pop() {
+                        self.close(closer);
+                    }
+                }
+                Ok(Event::Text(e)) => {
+                    // Unknown entities leave the text as written
+                    let text = e
+                        .unescape_with(html_entity)
+                        .map(|t| t.into_owned())
+                        .unwrap_or_else(|_| String::from_utf8_lossy(&e).into_owned());
+                    self.text(&text);
+                }
+                Ok(Event::CData(e)) => self.text(&String::from_utf8_lossy(&e)),
+                Ok(Event::Eof) => break,
+                Err(_) => {
+                    self.error = Some(reader.error_position());
+                    break;
+                }
+                Ok(_) => {}
+            }
+        }
+    }
+
+    fn finish(&self) -> String {
+        let mut lines: Vec<&str> = Vec::new();
+        for line in self.out.lines().map(str::trim_end) {
+            if line.is_empty() && lines.last().is_none_or(|l| l.is_empty()) {
+                continue;
+            }
+            lines.push(line);
+        }
+        lines.join("\n").trim().to_string()
+    }
+
+    fn push(&mut self, s: &str) {
+        self.out.push_str(s);
+    }
+
+    fn newline(&mut self) {
+        if self.cells > 0 {
+            if self.line_has_text {
+                self.out.push(' ');
+            }
+            return;
+        }
+        if self.line_has_text {
+            self.out.push('\n');
+            self.line_has_text = false;
+        }
+    }
+
+    fn blank_line(&mut self) {
+        if self.cells > 0 {
+            return;
+        }
+        self.newline();
+        // A list marker is waiting for its text
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            return;
+        }
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.crypt > 0 {
+            return;
+        }
+        if self.pre > 0 {
+            self.push(text);
+            self.line_has_text = !self.out.ends_with('\n');
+            return;
+        }
+        // Whitespace in HTML is layout; runs of it are one space, and none
+        // at the start of a line
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let mut collapsed = String::new();
+        if text.starts_with(char::is_whitespace) && self.line_has_text && !self.out.ends_with(' ') {
+            collapsed.push(' ');
+        }
+        collapsed.push_str(&words.join(" "));
+        if !words.is_empty() && text.ends_with(char::is_whitespace) {
+            collapsed.push(' ');
+        }
+        self.push(&collapsed);
+        if !words.is_empty() {
+            self.line_has_text = true;
+        }
+    }
+
+    fn open(&mut self, e: &BytesStart) -> Closer {
+        let name = String::from_utf8_lossy(e.local_name().as_ref()).to_lowercase();
+        let attr = |key: &str| -> Option<String> {
+            e.try_get_attribute(key)
+                .ok()
+                .flatten()
+                .and_then(|a| a.unescape_value().ok())
+                .map(|v| v.to_string())
+        };
+
+        if self.crypt > 0 {
+            return Closer::Nothing;
+        }
+
+        match name.as_str() {
+            "div" if attr("style").is_some_and(|s| s.contains("-en-codeblock")) => {
+                self.start_pre();
+                Closer::Pre
+            }
+            "div" | "en-note" | "section" | "article" | "center" => {
+                self.newline();
+                Closer::Block
+            }
+            "p" => {
+                self.blank_line();
+                Closer::Paragraph
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.blank_line();
+                let level = name[1..].parse().unwrap_or(1);
+                self.push(&format!("{} ", "#".repeat(level)));
+                Closer::Paragraph
+            }
+            "br" => {
+                if self.pre > 0 {
+                    self.push("\n");
+                } else if self.cells > 0 {
+                    self.push(" ");
+                } else {
+                    self.out.push('\n');
+                    self.line_has_text = false;
+                }
+                Closer::Nothing
+            }
+            "hr" => {
+                self.blank_line();
+                self.push("---");
+                self.line_has_text = true;
+                self.blank_line();
+                Closer::Nothing
+            }
+            "b" | "strong" => self.inline("**"),
+            "i" | "em" => self.inline("*"),
+            "s" | "strike" | "del" => self.inline("~~"),
+            "code" if self.pre == 0 => self.inline("`"),
+            "a" => match attr("href").filter(|h| !h.is_empty()) {
+                Some(href) => {
+                    self.push("[");
+                    Closer::Link(href)
+                }
+                None => Closer::Nothing,
+            },
+            "pre" => {
+                self.start_pre();
+                Closer::Pre
+            }
+            "ul" | "ol" => {
+                if self.lists.is_empty() {
+                    self.blank_line();
+                } else {
+                    self.newline();
+                }
+                self.lists.push(if name == "ol" { Some(1) } else { None });
+                Closer::List
+            }
+            "li" => {
+                self.newline();
+                let depth = self.lists.len().max(1);
+                let marker = match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{}. ", *n - 1)
+                    }
+                    _ => "- ".to_string(),
+                };
+                self.push(&format!("{}{}", "  ".repeat(depth - 1), marker));
+                Closer::Item
+            }
+            "en-todo" => {
+                let checked = attr("checked").is_some_and(|v| v == "true");
+                if !self.line_has_text && (self.out.is_empty() || self.out.ends_with('\n')) {
+                    self.push("- ");
+                }
+                self.push(if checked { "[x] " } else { "[ ] " });
+                Closer::Nothing
+            }
+            "en-media" => {
+                let hash = attr("hash").unwrap_or_default();
+                if let Some(link) = self.media.get(&hash) {
+                    let link = link.clone();
+                    if self.line_has_text && !self.out.ends_with(' ') {
+                        self.push(" ");
+                    }
+                    self.push(&link);
+                    self.line_has_text = true;
+                    self.used.insert(hash);
+                }
+                Closer::Nothing
+            }
+            "img" => {
+                if let Some(src) = attr("src").filter(|s| !s.is_empty()) {
+                    let alt = attr("alt").unwrap_or_default();
+                    self.push(&format!("![{}]({})", alt, src));
+                    self.line_has_text = true;
+                }
+                Closer::Nothing
+            }
+            "blockquote" => {
+                self.blank_line();
+                Closer::Quote(self.out.len())
+            }
+            "table" => {
+                self.blank_line();
+                self.tables.push((0, 0));
+                Closer::Table
+            }
+            "tr" => {
+                self.newline();
+                if let Some(t) = self.tables.last_mut() {
+                    t.1 = 0;
+                }
+                Closer::Row
+            }
+            "td" | "th" => {
+                self.push("| ");
+                self.cells += 1;
+                self.line_has_text = true;
+                if let Some(t) = self.tables.last_mut() {
+                    t.1 += 1;
+                }
+                Closer::Cell
+            }
+            "en-crypt" => {
+                self.crypt += 1;
+                self.encrypted += 1;
+                Closer::Crypt
+            }
+            _ => Closer::Nothing,
+        }
+    }
+
+    fn inline(&mut self, mark: &'static str) -> Closer {
+        self.push(mark);
+        Closer::Inline(mark)
+    }
+
+    fn start_pre(&mut self) {
+        self.blank_line();
+        self.push("```\n");
+        self.line_has_text = false;
+        self.pre += 1;
+    }
+
+    fn close(&mut self, closer: Closer) {
+        match closer {
+            Closer::Nothing => {}
+            Closer::Block | Closer::Item => self.newline(),
+            Closer::Paragraph => self.blank_line(),
+            Closer::Inline(mark) => {
+                // Markers around nothing, or around trailing space, don't render
+                if self.out.ends_with(mark) {
+                    self.out.truncate(self.out.len() - mark.len());
+                } else {
+                    let trimmed = self.out.trim_end_matches(' ').len();
+                    let spaces = self.out.len() - trimmed;
+                    self.out.truncate(trimmed);
+                    self.push(mark);
+                    self.push(&" ".repeat(spaces));
+                }
+            }
+            Closer::Link(href) => {
+                self.push(&format!("]({})", href));
+                self.line_has_text = true;
+            }
+            Closer::Pre => {
+                if !self.out.ends_with('\n') {
+                    self.out.push('\n');
+                }
+                self.push("```");
+                self.pre -= 1;
+                self.line_has_text = true;
+                self.blank_line();
+            }
+            Closer::List => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.blank_line();
+                }
+            }
+            Closer::Quote(start) => {
+                self.newline();
+                let quoted: Vec<String> = self.out[start..]
+                    .trim_end()
+                    .lines()
+                    .map(|l| if l.is_empty() { ">".to_string() } else { format!("> {}", l) })
+                    .collect();
+                self.out.truncate(start);
+                self.push(&quoted.join("\n"));
+                self.line_has_text = true;
+                self.blank_line();
+            }
+            Closer::Table => {
+                self.tables.pop();
+                self.blank_line();
+            }
+            Closer::Row => {
+                let (rows, cells) = self.tables.last().copied().unwrap_or((1, 0));
+                self.push("|");
+                if rows == 0 && cells > 0 {
+                    self.push(&format!("\n|{}", "---|".repeat(cells)));
+                }
+                if let Some(t) = self.tables.last_mut() {
+                    t.0 += 1;
+                }
+                self.line_has_text = true;
+                self.newline();
+            }
+            Closer::Cell => {
+                self.cells = self.cells.saturating_sub(1);
+                self.push(" ");
+            }
+            Closer::Crypt => self.crypt = self.crypt.saturating_sub(1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_padding_and_whitespace() {
+        assert_eq!(decode_base64("aGk=").unwrap(), b"hi");
+        assert_eq!(decode_base64("aGk").unwrap(), b"hi");
+        assert_eq!(decode_base64("aGVs\n bG8=\r\n").unwrap(), b"hello");
+        assert_eq!(decode_base64("/+8=").unwrap(), decode_base64("_-8").unwrap());
+        assert_eq!(decode_base64("").unwrap(), b"");
+    }
+
+    #[test]
+    fn base64_invalid_input() {
+        for bad in ["aGk*", "aGk=aGk=", "a", "aGVsb", "aG!k"] {
+            assert!(decode_base64(bad).is_none(), "{}", bad);
+        }
+    }
+}
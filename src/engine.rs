@@ -0,0 +1,557 @@
+//! The template language behind `notectl new` and daily notes.
+//!
+//! Besides plain `{name}` placeholders, templates may use:
+//!
+//! - `{{ expr }}` to print a value, e.g. `{{ date | add_days(-1) }}`
+//! - `{{#if expr}} ... {{else}} ... {{/if}}`
+//! - `{{#each expr}} ... {{/each}}`, where the item's fields, `this`,
+//!   `@index`, `@first` and `@last` are in scope
+//!
+//! Values come from a JSON context, so anything `Serialize` can be exposed.
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime};
+use std::fmt::Write;
+use serde_json::{Map, Value};
+
+/// The output of a render, plus any `{name}` placeholders that had no value.
+/// Those are left in the text as written.
+pub struct Rendered {
+    pub text: String,
+    pub missing: Vec<String>,
+}
+
+pub fn render(src: &str, context: &Value) -> Result<Rendered> {
+    let tokens = tokenize(src)?;
+    let mut pos = 0;
+    let nodes = parse_nodes(&tokens, &mut pos, None)?;
+
+    let mut out = Rendered {
+        text: String::new(),
+        missing: Vec::new(),
+    };
+    let mut scopes = vec![Scope {
+        item: context.clone(),
+        index: 0,
+        len: 1,
+    }];
+    render_nodes(&nodes, &mut scopes, &mut out)?;
+    Ok(out)
+}
+
+enum Token {
+    Text(String),
+    Placeholder(String),
+    Expr(String),
+    If(String),
+    Each(String),
+    Else,
+    EndIf,
+    EndEach,
+}
+
+enum Node {
+    Text(String),
+    Placeholder(String),
+    Expr(Expr),
+    If(Expr, Vec<Node>, Vec<Node>),
+    Each(Expr, Vec<Node>),
+}
+
+struct Expr {
+    operand: Operand,
+    filters: Vec<(String, Vec<Operand>)>,
+}
+
+enum Operand {
+    Path(Vec<String>),
+    Literal(Value),
+}
+
+struct Scope {
+    item: Value,
+    index: usize,
+    len: usize,
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut rest = src;
+
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(inner) = rest.strip_prefix("{{") {
+            let end = inner
+                .find("}}")
+                .ok_or_else(|| anyhow!("unclosed '{{{{' in template"))?;
+            let tag = inner[..end].trim();
+            rest = &inner[end + 2..];
+
+            if !text.is_empty() {
+                tokens.push(Token::Text(std::mem::take(&mut text)));
+            }
+            tokens.push(match tag {
+                "else" => Token::Else,
+                "/if" => Token::EndIf,
+                "/each" => Token::EndEach,
+                _ => {
+                    if let Some(cond) = tag.strip_prefix("#if") {
+                        Token::If(cond.trim().to_string())
+                    } else if let Some(list) = tag.strip_prefix("#each") {
+                        Token::Each(list.trim().to_string())
+                    } else if tag.starts_with('#') || tag.starts_with('/') {
+                        bail!("unknown block '{{{{{}}}}}'", tag);
+                    } else {
+                        Token::Expr(tag.to_string())
+                    }
+                }
+            });
+            continue;
+        }
+
+        // A single-brace placeholder is `{` + identifier + `}`; anything else
+        // is ordinary text
+        let inner = &rest[1..];
+        let len = inner
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+            .unwrap_or(inner.len());
+        let name = &inner[..len];
+        if name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && inner[len..].starts_with('}')
+        {
+            if !text.is_empty() {
+                tokens.push(Token::Text(std::mem::take(&mut text)));
+            }
+            tokens.push(Token::Placeholder(name.to_string()));
+            rest = &inner[len + 1..];
+        } else {
+            text.push('{');
+            rest = inner;
+        }
+    }
+
+    text.push_str(rest);
+    if !text.is_empty() {
+        tokens.push(Token::Text(text));
+    }
+    Ok(tokens)
+}
+
+/// Parse nodes until the closing tag of `block` ("if" or "each"), or the end
+/// of input at the top level. Leaves `pos` on the closing token.
+fn parse_nodes(tokens: &[Token], pos: &mut usize, block: Option<&str>) -> Result<Vec<Node>> {
+    let mut nodes = Vec::new();
+
+    while *pos < tokens.len() {
+        match &tokens[*pos] {
+            Token::Text(t) => nodes.push(Node::Text(t.clone())),
+            Token::Placeholder(name) => nodes.push(Node::Placeholder(name.clone())),
+            Token::Expr(e) => nodes.push(Node::Expr(parse_expr(e)?)),
+            Token::If(cond) => {
+                let cond = parse_expr(cond)?;
+                *pos += 1;
+                let then = parse_nodes(tokens, pos, Some("if"))?;
+                let otherwise = if matches!(tokens.get(*pos), Some(Token::Else)) {
+                    *pos += 1;
+                    parse_nodes(tokens, pos, Some("if"))?
+                } else {
+                    Vec::new()
+                };
+                if !matches!(tokens.get(*pos), Some(Token::EndIf)) {
+                    bail!("'{{{{#if}}}}' without '{{{{/if}}}}'");
+                }
+                nodes.push(Node::If(cond, then, otherwise));
+            }
+            Token::Each(list) => {
+                let list = parse_expr(list)?;
+                *pos += 1;
+                let body = parse_nodes(tokens, pos, Some("each"))?;
+                if !matches!(tokens.get(*pos), Some(Token::EndEach)) {
+                    bail!("'{{{{#each}}}}' without '{{{{/each}}}}'");
+                }
+                nodes.push(Node::Each(list, body));
+            }
+            Token::Else if block == Some("if") => return Ok(nodes),
+            Token::EndIf if block == Some("if") => return Ok(nodes),
+            Token::EndEach if block == Some("each") => return Ok(nodes),
+            Token::Else => bail!("'{{{{else}}}}' outside '{{{{#if}}}}'"),
+            Token::EndIf => bail!("unexpected '{{{{/if}}}}'"),
+            Token::EndEach => bail!("unexpected '{{{{/each}}}}'"),
+        }
+        *pos += 1;
+    }
+
+    Ok(nodes)
+}
+
+fn parse_expr(src: &str) -> Result<Expr> {
+    let mut parts = split_outside_quotes(src, '|').into_iter();
+    let operand = parse_operand(parts.next().unwrap_or_default().trim())?;
+
+    let mut filters = Vec::new();
+    for part in parts {
+        let part = part.trim();
+        let (name, args) = match part.split_once('(') {
+            Some((name, args)) => {
+                let args = args
+                    .strip_suffix(')')
+                    .ok_or_else(|| anyhow!("missing ')' in filter '{}'", part))?;
+                let args = split_outside_quotes(args, ',')
+                    .iter()
+                    .map(|a| a.trim())
+                    .filter(|a| !a.is_empty())
+                    .map(parse_operand)
+                    .collect::<Result<Vec<_>>>()?;
+                (name.trim(), args)
+            }
+            None => (part, Vec::new()),
+        };
+        filters.push((name.to_string(), args));
+    }
+
+    Ok(Expr { operand, filters })
+}
+
+fn parse_operand(src: &str) -> Result<Operand> {
+    if src.is_empty() {
+        bail!("empty expression");
+    }
+    if let Some(s) = src.strip_prefix('"') {
+        let s = s
+            .strip_suffix('"')
+            .ok_or_else(|| anyhow!("unterminated string {}", src))?;
+        return Ok(Operand::Literal(Value::String(s.to_string())));
+    }
+    if let Ok(n) = src.parse::<i64>() {
+        return Ok(Operand::Literal(Value::from(n)));
+    }
+    match src {
+        "true" => Ok(Operand::Literal(Value::Bool(true))),
+        "false" => Ok(Operand::Literal(Value::Bool(false))),
+        _ => Ok(Operand::Path(src.split('.').map(|s| s.to_string()).collect())),
+    }
+}
+
+fn split_outside_quotes(src: &str, sep: char) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in src.chars() {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        if c == sep && !quoted {
+            parts.push(std::mem::take(&mut current));
+        } else {
+            current.push(c);
+        }
+    }
+    parts.push(current);
+    parts
+}
+
+fn render_nodes(nodes: &[Node], scopes: &mut Vec<Scope>, out: &mut Rendered) -> Result<()> {
+    for node in nodes {
+        match node {
+            Node::Text(t) => out.text.push_str(t),
+            Node::Placeholder(name) => match lookup(scopes, std::slice::from_ref(name)) {
+                Value::Null => {
+                    out.text.push_str(&format!("{{{}}}", name));
+                    if !out.missing.contains(name) {
+                        out.missing.push(name.clone());
+                    }
+                }
+                value => out.text.push_str(&to_text(&value)),
+            },
+            Node::Expr(expr) => out.text.push_str(&to_text(&eval(expr, scopes)?)),
+            Node::If(cond, then, otherwise) => {
+                let branch = if truthy(&eval(cond, scopes)?) {
+                    then
+                } else {
+                    otherwise
+                };
+                render_nodes(branch, scopes, out)?;
+            }
+            Node::Each(list, body) => {
+                let items = match eval(list, scopes)? {
+                    Value::Array(items) => items,
+                    Value::Null => Vec::new(),
+                    other => vec![other],
+                };
+                let len = items.len();
+                for (index, item) in items.into_iter().enumerate() {
+                    scopes.push(Scope { item, index, len });
+                    let result = render_nodes(body, scopes, out);
+                    scopes.pop();
+                    result?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn eval(expr: &Expr, scopes: &[Scope]) -> Result<Value> {
+    let mut value = operand_value(&expr.operand, scopes);
+    for (name, args) in &expr.filters {
+        let args: Vec<Value> = args.iter().map(|a| operand_value(a, scopes)).collect();
+        value = apply_filter(name, value, &args)?;
+    }
+    Ok(value)
+}
+
+fn operand_value(operand: &Operand, scopes: &[Scope]) -> Value {
+    match operand {
+        Operand::Literal(v) => v.clone(),
+        Operand::Path(path) => lookup(scopes, path),
+    }
+}
+
+/// Resolve a dotted path against the innermost scope that defines its first
+/// segment, so loop items shadow outer variables.
+fn lookup(scopes: &[Scope], path: &[String]) -> Value {
+    let scope = match scopes.last() {
+        Some(s) => s,
+        None => return Value::Null,
+    };
+
+    let (root, rest) = match path[0].as_str() {
+        "this" => (scope.item.clone(), &path[1..]),
+        "@index" => (Value::from(scope.index), &path[1..]),
+        "@first" => (Value::Bool(scope.index == 0), &path[1..]),
+        "@last" => (Value::Bool(scope.index + 1 == scope.len), &path[1..]),
+        first => match scopes.iter().rev().find_map(|s| s.item.get(first)) {
+            Some(v) => (v.clone(), &path[1..]),
+            None => return Value::Null,
+        },
+    };
+
+    rest.iter()
+        .try_fold(root, |v, key| v.get(key).cloned())
+        .unwrap_or(Value::Null)
+}
+
+fn apply_filter(name: &str, value: Value, args: &[Value]) -> Result<Value> {
+    let arg = |i: usize| {
+        args.get(i)
+            .ok_or_else(|| anyhow!("filter '{}' needs {} argument(s)", name, i + 1))
+    };
+
+    Ok(match name {
+        "add_days" => {
+            let days = arg(0)?
+                .as_i64()
+                .ok_or_else(|| anyhow!("add_days expects a number"))?;
+            match parse_date(&value) {
+                Some(dt) => {
+                    let date = Duration::try_days(days)
+                        .and_then(|d| dt.date().checked_add_signed(d))
+                        .ok_or_else(|| anyhow!("add_days({}) is out of range", days))?;
+                    Value::String(date.to_string())
+                }
+                None => Value::Null,
+            }
+        }
+        "format" => {
+            let fmt = arg(0)?
+                .as_str()
+                .ok_or_else(|| anyhow!("format expects a string"))?;
+            match parse_date(&value) {
+                Some(dt) => {
+                    // An invalid specifier only shows up while formatting
+                    let mut text = String::new();
+                    if write!(text, "{}", dt.format(fmt)).is_err() {
+                        bail!("invalid date format '{}'", fmt);
+                    }
+                    Value::String(text)
+                }
+                None => Value::Null,
+            }
+        }
+        "upper" => Value::String(to_text(&value).to_uppercase()),
+        "lower" => Value::String(to_text(&value).to_lowercase()),
+        "default" => {
+            if truthy(&value) {
+                value
+            } else {
+                arg(0)?.clone()
+            }
+        }
+        "length" => Value::from(match &value {
+            Value::Array(a) => a.len(),
+            Value::Object(o) => o.len(),
+            Value::Null => 0,
+            other => to_text(other).chars().count(),
+        }),
+        "join" => {
+            let sep = arg(0).ok().and_then(|a| a.as_str()).unwrap_or(", ");
+            match value {
+                Value::Array(items) => {
+                    let parts: Vec<String> = items.iter().map(to_text).collect();
+                    Value::String(parts.join(sep))
+                }
+                other => other,
+            }
+        }
+        "limit" => {
+            let n = arg(0)?
+                .as_u64()
+                .ok_or_else(|| anyhow!("limit expects a positive number"))?;
+            match value {
+                Value::Array(items) => Value::Array(items.into_iter().take(n as usize).collect()),
+                other => other,
+            }
+        }
+        "where" => {
+            let field = arg(0)?
+                .as_str()
+                .ok_or_else(|| anyhow!("where expects a field name"))?;
+            let wanted = to_text(arg(1)?);
+            match value {
+                Value::Array(items) => Value::Array(
+                    items
+                        .into_iter()
+                        .filter(|item| item.get(field).map(to_text).as_deref() == Some(&wanted))
+                        .collect(),
+                ),
+                other => other,
+            }
+        }
+        _ => bail!("unknown filter '{}'", name),
+    })
+}
+
+/// Read `YYYY-MM-DD`, `YYYY-MM-DD HH:MM` or RFC 3339 strings, the forms
+/// dates take in the template context.
+fn parse_date(value: &Value) -> Option<NaiveDateTime> {
+    let s = value.as_str()?;
+    if let Ok(d) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return d.and_hms_opt(0, 0, 0);
+    }
+    if let Ok(dt) = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M") {
+        return Some(dt);
+    }
+    DateTime::parse_from_rfc3339(s).ok().map(|dt| dt.naive_local())
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64() != Some(0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(a) => !a.is_empty(),
+        Value::Object(o) => !o.is_empty(),
+    }
+}
+
+fn to_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => {
+            let parts: Vec<String> = items.iter().map(to_text).collect();
+            parts.join(", ")
+        }
+        other => other.to_string(),
+    }
+}
+
+/// Build a context object from string variables.
+pub fn context_from(vars: &[(&str, &str)]) -> Map<String, Value> {
+    vars.iter()
+        .map(|(k, v)| (k.to_string(), Value::String(v.to_string())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render_str(src: &str, context: Value) -> Result<String> {
+        render(src, &context).map(|r| r.text)
+    }
+
+    #[test]
+    fn literal_text_is_kept() {
+        assert_eq!(render_str("plain { text }", json!({})).unwrap(), "plain { text }");
+        assert_eq!(render_str("", json!({})).unwrap(), "");
+    }
+
+    #[test]
+    fn placeholders() {
+        let out = render("{title} by {author}", &json!({"title": "Plan"})).unwrap();
+        assert_eq!(out.text, "Plan by {author}");
+        assert_eq!(out.missing, vec!["author".to_string()]);
+    }
+
+    #[test]
+    fn expressions_and_blocks() {
+        let context = json!({"tags": ["a", "b"], "done": false, "user": {"name": "Ana"}});
+        assert_eq!(render_str("{{ user.name | upper }}", context.clone()).unwrap(), "ANA");
+        assert_eq!(
+            render_str("{{#if done}}yes{{else}}no{{/if}}", context.clone()).unwrap(),
+            "no"
+        );
+        assert_eq!(
+            render_str("{{#each tags}}{{@index}}:{{this}}{{#if @last}}.{{else}},{{/if}}{{/each}}", context)
+                .unwrap(),
+            "0:a,1:b."
+        );
+    }
+
+    #[test]
+    fn filters() {
+        let context = json!({
+            "date": "2026-02-14",
+            "items": [{"s": "x"}, {"s": "y"}, {"s": "x"}],
+            "empty": ""
+        });
+        let cases = [
+            ("{{ date | add_days(-14) }}", "2026-01-31"),
+            ("{{ date | format(\"%d/%m/%Y\") }}", "14/02/2026"),
+            ("{{ empty | default(\"none\") }}", "none"),
+            ("{{ items | length }}", "3"),
+            ("{{ items | where(\"s\", \"x\") | length }}", "2"),
+            ("{{ items | limit(1) | length }}", "1"),
+            ("{{ \"A|B\" | lower }}", "a|b"),
+        ];
+        for (src, expected) in cases {
+            assert_eq!(render_str(src, context.clone()).unwrap(), expected, "{}", src);
+        }
+    }
+
+    #[test]
+    fn unclosed_tags_are_errors() {
+        for src in [
+            "{{ date",
+            "{{#if x}}open",
+            "{{#each x}}open",
+            "{{/if}}",
+            "{{else}}",
+            "{{#unless x}}{{/unless}}",
+        ] {
+            assert!(render_str(src, json!({})).is_err(), "{}", src);
+        }
+    }
+
+    #[test]
+    fn filter_errors() {
+        let context = json!({"date": "2026-02-14"});
+        for src in [
+            "{{ date | nope }}",
+            "{{ date | add_days }}",
+            "{{ date | add_days(\"x\") }}",
+            "{{ date | add_days(999999999999) }}",
+            "{{ date | format(\"%Q\") }}",
+            "{{ date | format(1 }}",
+            "{{ \"open }}",
+        ] {
+            assert!(render_str(src, context.clone()).is_err(), "{}", src);
+        }
+    }
+}
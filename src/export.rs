@@ -1,31 +1,57 @@
 use chrono::Local;
 use rusqlite::{params, Connection, Result as SqlResult};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
 
+use crate::csvfile;
+use crate::ics;
+use crate::org;
+use crate::site;
 use crate::note::Note;
-use crate::utils::timestamp_to_local;
+use crate::utils::{inline_hashtags, note_title, slugify, split_front_matter, timestamp_to_local};
 
-#[derive(Serialize)]
-struct ExportNote {
-    id: i64,
-    content: String,
-    created_at: String,
-    updated_at: String,
-    category: Option<String>,
-    tags: Vec<String>,
+/// A note as written by `export --format json` and read back by `import`.
+/// Timestamps are local time in `EXPORT_TIME_FORMAT`.
+#[derive(Serialize, Deserialize)]
+pub struct ExportNote {
+    pub id: i64,
+    pub content: String,
+    pub created_at: String,
+    pub updated_at: String,
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+    /// Missing from exports made before daily notes were included
+    #[serde(default)]
+    pub is_daily: bool,
 }
 
+pub const EXPORT_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
 pub fn export_notes(
     conn: &Connection,
     format: &str,
     tag: Option<&str>,
     from: Option<&str>,
     to: Option<&str>,
-) -> SqlResult<String> {
+    journal: bool,
+    columns: Option<&str>,
+) -> anyhow::Result<String> {
     let notes = fetch_export_notes(conn, tag, from, to)?;
 
     match format {
         "json" => Ok(export_json(&notes)),
+        "csv" => csvfile::write_notes(&notes, columns),
+        "org" => Ok(org::export(conn, tag, &notes)?),
+        "ics" | "ical" => {
+            let dailies: Vec<Note> = if journal {
+                notes.into_iter().filter(|n| n.is_daily).collect()
+            } else {
+                Vec::new()
+            };
+            Ok(ics::export(conn, tag, &dailies)?)
+        }
         "markdown" | "md" => Ok(export_markdown(&notes)),
         _ => Ok(export_markdown(&notes)),
     }
@@ -130,10 +156,11 @@ fn export_json(notes: &[Note]) -> String {
         .map(|n| ExportNote {
             id: n.id,
             content: n.content.clone(),
-            created_at: n.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
-            updated_at: n.updated_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+            created_at: n.created_at.format(EXPORT_TIME_FORMAT).to_string(),
+            updated_at: n.updated_at.format(EXPORT_TIME_FORMAT).to_string(),
             category: n.category.clone(),
             tags: n.tags.clone(),
+            is_daily: n.is_daily,
         })
         .collect();
 
@@ -165,3 +192,301 @@ fn export_markdown(notes: &[Note]) -> String {
 
     md
 }
+
+/// Write the selected notes to `dir` as a static HTML site.
+pub fn export_html(
+    conn: &Connection,
+    tag: Option<&str>,
+    from: Option<&str>,
+    to: Option<&str>,
+    dir: &Path,
+) -> anyhow::Result<site::SiteReport> {
+    let notes = fetch_export_notes(conn, tag, from, to)?;
+    site::write(&notes, dir)
+}
+
+/// Front matter written at the top of each file by `export_to_dir`. The
+/// Markdown importer reads the same fields back.
+#[derive(Serialize)]
+struct FileFrontMatter<'a> {
+    id: i64,
+    created: String,
+    updated: String,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    tags: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    category: Option<&'a str>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    daily: bool,
+}
+
+/// How `export_to_dir` arranges notes into files.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DirLayout {
+    /// Slugged file names in a single folder, metadata in front matter
+    Flat,
+    /// An Obsidian vault: daily notes in `Daily/YYYY-MM-DD.md`, other notes
+    /// named after their title in one folder per category, tags inline
+    Obsidian,
+}
+
+impl DirLayout {
+    /// Folder holding daily notes in an Obsidian vault.
+    pub const DAILY_FOLDER: &'static str = "Daily";
+
+    /// Path of the note's file relative to the export directory, without
+    /// the `.md` extension.
+    fn base(self, n: &Note) -> String {
+        match self {
+            DirLayout::Flat => {
+                slugify(note_title(&n.content)).unwrap_or_else(|| format!("note-{}", n.id))
+            }
+            DirLayout::Obsidian if n.is_daily => format!(
+                "{}/{}",
+                Self::DAILY_FOLDER,
+                n.created_at.format("%Y-%m-%d")
+            ),
+            DirLayout::Obsidian => {
+                let name = vault_name(note_title(&n.content))
+                    .unwrap_or_else(|| format!("Note {}", n.id));
+                let folders: Vec<String> = n
+                    .category
+                    .iter()
+                    .flat_map(|c| c.split('/'))
+                    .filter_map(vault_name)
+                    .collect();
+                folders
+                    .into_iter()
+                    .chain(std::iter::once(name))
+                    .collect::<Vec<_>>()
+                    .join("/")
+            }
+        }
+    }
+
+    /// Joins a base name and the counter that makes it unique.
+    fn counter_separator(self) -> char {
+        match self {
+            DirLayout::Flat => '-',
+            DirLayout::Obsidian => ' ',
+        }
+    }
+
+    fn render(self, n: &Note) -> anyhow::Result<String> {
+        let created = n.created_at.format(EXPORT_TIME_FORMAT).to_string();
+        let updated = n.updated_at.format(EXPORT_TIME_FORMAT).to_string();
+        let front = match self {
+            DirLayout::Flat => FileFrontMatter {
+                id: n.id,
+                created,
+                updated,
+                tags: &n.tags,
+                category: n.category.as_deref(),
+                daily: n.is_daily,
+            },
+            // The folder already says whether a note is daily and what its
+            // category is, and Obsidian shows tags where they are written
+            DirLayout::Obsidian => FileFrontMatter {
+                id: n.id,
+                created,
+                updated,
+                tags: &[],
+                category: None,
+                daily: false,
+            },
+        };
+
+        let mut body = n.content.clone();
+        if self == DirLayout::Obsidian {
+            let inline: Vec<String> = inline_hashtags(&n.content)
+                .into_iter()
+                .map(|t| t.to_lowercase())
+                .collect();
+            let missing: Vec<String> = n
+                .tags
+                .iter()
+                .map(|t| t.split_whitespace().collect::<Vec<_>>().join("-"))
+                .filter(|t| !t.is_empty() && !inline.contains(&t.to_lowercase()))
+                .map(|t| format!("#{}", t))
+                .collect();
+            if !missing.is_empty() {
+                body = format!("{}\n\n{}", body.trim_end(), missing.join(" "));
+            }
+        }
+
+        Ok(format!(
+            "---\n{}---\n\n{}\n",
+            serde_norway::to_string(&front)?,
+            body
+        ))
+    }
+}
+
+pub struct DirExportReport {
+    pub written: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+}
+
+/// Write one Markdown file per note into `dir`, arranged by `layout`.
+/// Re-exporting into the same directory only rewrites files whose note
+/// changed, a note whose title changed moves to its new file name, and files
+/// of notes no longer in the export are removed. Existing files are matched
+/// to notes by the `id` in their front matter; files without one are left
+/// alone.
+pub fn export_to_dir(
+    conn: &Connection,
+    tag: Option<&str>,
+    from: Option<&str>,
+    to: Option<&str>,
+    dir: &Path,
+    layout: DirLayout,
+) -> anyhow::Result<DirExportReport> {
+    let mut notes = fetch_export_notes(conn, tag, from, to)?;
+    notes.sort_by_key(|n| n.id);
+
+    // A flat export only owns the top level; a vault owns every folder
+    let recursive = layout == DirLayout::Obsidian;
+
+    fs::create_dir_all(dir)?;
+    let mut taken = HashSet::new();
+    md_stems(dir, "", recursive, &mut taken)?;
+    let existing = existing_exports(dir, &taken);
+
+    let mut report = DirExportReport {
+        written: 0,
+        unchanged: 0,
+        removed: 0,
+    };
+
+    // Notes deleted, or no longer matching the filters, go first so their
+    // names are free again
+    let selected: HashSet<i64> = notes.iter().map(|n| n.id).collect();
+    for (id, stem) in &existing {
+        if !selected.contains(id) {
+            remove_export(dir, stem)?;
+            taken.remove(stem);
+            report.removed += 1;
+        }
+    }
+
+    for n in &notes {
+        let base = layout.base(n);
+        let sep = layout.counter_separator();
+        let previous = existing.get(&n.id);
+
+        // A numbered name is only kept while it is still the one a fresh
+        // export would pick
+        let stem = match previous {
+            Some(prev) if prev == &base => prev.clone(),
+            _ => {
+                if let Some(prev) = previous {
+                    taken.remove(prev);
+                }
+                let mut candidate = base.clone();
+                let mut i = 2;
+                while taken.contains(&candidate) {
+                    candidate = format!("{}{}{}", base, sep, i);
+                    i += 1;
+                }
+                candidate
+            }
+        };
+        taken.insert(stem.clone());
+
+        if let Some(prev) = previous.filter(|p| **p != stem) {
+            remove_export(dir, prev)?;
+        }
+
+        let path = dir.join(format!("{}.md", stem));
+        let rendered = layout.render(n)?;
+        if fs::read_to_string(&path).is_ok_and(|current| current == rendered) {
+            report.unchanged += 1;
+        } else {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, rendered)?;
+            report.written += 1;
+        }
+    }
+
+    Ok(report)
+}
+
+/// Map note ids to the file stems they were previously exported under.
+fn existing_exports(dir: &Path, stems: &HashSet<String>) -> HashMap<i64, String> {
+    #[derive(Deserialize)]
+    struct Id {
+        id: Option<i64>,
+    }
+
+    let mut found = HashMap::new();
+    for stem in stems {
+        let content = match fs::read_to_string(dir.join(format!("{}.md", stem))) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let id = split_front_matter(&content)
+            .and_then(|(yaml, _)| serde_norway::from_str::<Id>(yaml).ok())
+            .and_then(|f| f.id);
+        if let Some(id) = id {
+            found.insert(id, stem.clone());
+        }
+    }
+    found
+}
+
+/// Collect `.md` files under `dir` as `/`-separated paths without the
+/// extension, skipping hidden entries such as `.obsidian`. Symlinked folders
+/// aren't followed, so a link back up the tree can't loop.
+fn md_stems(dir: &Path, prefix: &str, recursive: bool, stems: &mut HashSet<String>) -> anyhow::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(n) if !n.starts_with('.') => n,
+            _ => continue,
+        };
+        if entry.file_type()?.is_dir() {
+            if recursive {
+                md_stems(&path, &format!("{}{}/", prefix, name), true, stems)?;
+            }
+        } else if let Some(stem) = name.strip_suffix(".md") {
+            stems.insert(format!("{}{}", prefix, stem));
+        }
+    }
+    Ok(())
+}
+
+/// Delete an exported file, then any folders that leaves empty.
+fn remove_export(dir: &Path, stem: &str) -> anyhow::Result<()> {
+    let old = dir.join(format!("{}.md", stem));
+    fs::remove_file(&old)?;
+    // remove_dir refuses folders that still hold something
+    for parent in old.ancestors().skip(1).take_while(|p| *p != dir) {
+        if fs::remove_dir(parent).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// `title` made safe for an Obsidian file or folder name: characters that
+/// are invalid in file names or break `[[links]]` are dropped.
+fn vault_name(title: &str) -> Option<String> {
+    let cleaned: String = title
+        .chars()
+        .filter(|c| !matches!(c, '\\' | '/' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '#' | '^' | '[' | ']'))
+        .collect();
+    let name: String = cleaned
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_start_matches('.')
+        .chars()
+        .take(100)
+        .collect();
+    let name = name.trim_end().to_string();
+    (!name.is_empty()).then_some(name)
+}
@@ -0,0 +1,140 @@
+use chrono::{DateTime, Local, Utc};
+use rusqlite::{Connection, Result as SqlResult};
+
+use crate::db;
+use crate::note::Note;
+use crate::todo::{self, Todo, TodoFilter};
+
+/// Render TODOs as `VTODO` components and, optionally, daily notes as
+/// `VJOURNAL` entries. UIDs derive from row ids and the database's instance
+/// id, so calendar apps update existing entries on re-import instead of
+/// duplicating them, and exports from two databases never clash.
+pub fn export(conn: &Connection, tag: Option<&str>, journal: &[Note]) -> SqlResult<String> {
+    let instance = db::instance_id(conn)?;
+    let filter = TodoFilter {
+        tag,
+        include_deferred: true,
+        ..Default::default()
+    };
+    let todos = todo::list_todos(conn, &filter)?;
+    let stamp = format_utc(&Local::now());
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:-//notectl//notectl {}//EN", env!("CARGO_PKG_VERSION")),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+
+    for t in &todos {
+        push_vtodo(&mut lines, t, &stamp, &instance);
+    }
+    for n in journal {
+        push_vjournal(&mut lines, n, &stamp, &instance);
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut out = String::new();
+    for line in &lines {
+        out.push_str(&fold(line));
+        out.push_str("\r\n");
+    }
+    Ok(out)
+}
+
+fn push_vtodo(lines: &mut Vec<String>, t: &Todo, stamp: &str, instance: &str) {
+    lines.push("BEGIN:VTODO".to_string());
+    lines.push(format!("UID:todo-{}-{}@notectl", t.id, instance));
+    lines.push(format!("DTSTAMP:{}", stamp));
+    lines.push(format!("CREATED:{}", format_utc(&t.created_at)));
+    lines.push(format!("SUMMARY:{}", escape(&t.task)));
+
+    let priority = match t.priority.as_str() {
+        "high" => 1,
+        "low" => 9,
+        _ => 5,
+    };
+    lines.push(format!("PRIORITY:{}", priority));
+
+    let status = match t.status.as_str() {
+        "done" => "COMPLETED",
+        "cancelled" => "CANCELLED",
+        "doing" => "IN-PROCESS",
+        _ => "NEEDS-ACTION",
+    };
+    lines.push(format!("STATUS:{}", status));
+
+    // Due dates are stored as the end of the local day, so emit them as dates
+    if let Some(due) = t.due_date {
+        lines.push(format!("DUE;VALUE=DATE:{}", due.format("%Y%m%d")));
+    }
+    if let Some(start) = t.defer_until {
+        lines.push(format!("DTSTART;VALUE=DATE:{}", start.format("%Y%m%d")));
+    }
+    if let Some(done) = t.completed_at {
+        lines.push(format!("COMPLETED:{}", format_utc(&done)));
+    }
+
+    let mut categories: Vec<String> = t.project.iter().cloned().collect();
+    categories.extend(t.tags.iter().cloned());
+    if !categories.is_empty() {
+        let escaped: Vec<String> = categories.iter().map(|c| escape(c)).collect();
+        lines.push(format!("CATEGORIES:{}", escaped.join(",")));
+    }
+
+    lines.push("END:VTODO".to_string());
+}
+
+fn push_vjournal(lines: &mut Vec<String>, n: &Note, stamp: &str, instance: &str) {
+    let summary = n
+        .content
+        .lines()
+        .next()
+        .unwrap_or("")
+        .trim_start_matches('#')
+        .trim();
+
+    lines.push("BEGIN:VJOURNAL".to_string());
+    lines.push(format!("UID:note-{}-{}@notectl", n.id, instance));
+    lines.push(format!("DTSTAMP:{}", stamp));
+    lines.push(format!("DTSTART;VALUE=DATE:{}", n.created_at.format("%Y%m%d")));
+    lines.push(format!("CREATED:{}", format_utc(&n.created_at)));
+    lines.push(format!("LAST-MODIFIED:{}", format_utc(&n.updated_at)));
+    lines.push(format!("SUMMARY:{}", escape(summary)));
+    lines.push(format!("DESCRIPTION:{}", escape(&n.content)));
+    if !n.tags.is_empty() {
+        let escaped: Vec<String> = n.tags.iter().map(|c| escape(c)).collect();
+        lines.push(format!("CATEGORIES:{}", escaped.join(",")));
+    }
+    lines.push("END:VJOURNAL".to_string());
+}
+
+fn format_utc(dt: &DateTime<Local>) -> String {
+    dt.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escape a TEXT value per RFC 5545 section 3.3.11.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Fold content lines longer than 75 octets, never splitting a UTF-8 char.
+fn fold(line: &str) -> String {
+    let mut out = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        let len = c.len_utf8();
+        if width + len > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += len;
+    }
+    out
+}
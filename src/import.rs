@@ -0,0 +1,360 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+use rusqlite::{params, Connection};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::export::{ExportNote, EXPORT_TIME_FORMAT};
+use crate::note::{self, Note};
+use crate::utils::{deserialize_tags, inline_hashtags, split_front_matter};
+
+/// How imported notes combine with the ones already in the database.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    /// Keep existing notes and add the ones not already present
+    Merge,
+    /// Delete every existing note first, then restore the file as-is
+    Replace,
+}
+
+/// What makes an imported note a duplicate of an existing one in merge mode.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Match {
+    /// A note with identical content exists
+    Content,
+    /// A note with the same id exists
+    Id,
+}
+
+impl Match {
+    pub fn parse(s: &str) -> Option<Match> {
+        match s {
+            "content" => Some(Match::Content),
+            "id" => Some(Match::Id),
+            _ => None,
+        }
+    }
+}
+
+pub struct ImportOptions {
+    pub mode: Mode,
+    pub matching: Match,
+    /// Run the import and report the outcome, then roll it back
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub skipped: usize,
+    /// Existing notes deleted by `Mode::Replace`
+    pub removed: usize,
+    /// Indices into the imported notes of those that were written
+    pub inserted: Vec<usize>,
+}
+
+/// Load notes from a `notectl export --format json` file.
+pub fn parse_json(content: &str) -> Result<Vec<Note>> {
+    let records: Vec<ExportNote> =
+        serde_json::from_str(content).context("not a notectl JSON export")?;
+
+    records
+        .into_iter()
+        .map(|r| {
+            Ok(Note {
+                id: r.id,
+                created_at: parse_time(&r.created_at)?,
+                updated_at: parse_time(&r.updated_at)?,
+                content: r.content,
+                category: r.category,
+                is_daily: r.is_daily,
+                tags: r.tags,
+            })
+        })
+        .collect()
+}
+
+/// Read a timestamp in the export format, RFC 3339, `YYYY-MM-DD HH:MM`,
+/// `YYYY-MM-DDTHH:MM:SS` or a bare date (midnight local time).
+pub fn parse_time(s: &str) -> Result<DateTime<Local>> {
+    let s = s.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Ok(dt.with_timezone(&Local));
+    }
+    let naive = [EXPORT_TIME_FORMAT, "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(s, fmt).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        });
+    naive
+        .and_then(|dt| dt.and_local_timezone(Local).earliest())
+        .with_context(|| format!("invalid timestamp '{}'", s))
+}
+
+/// Front matter fields understood when importing Markdown files. Anything
+/// else in the block is ignored.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct NoteFrontMatter {
+    id: Option<i64>,
+    #[serde(deserialize_with = "deserialize_tags")]
+    tags: Vec<String>,
+    category: Option<String>,
+    #[serde(alias = "date", alias = "created_at")]
+    created: Option<String>,
+    #[serde(alias = "updated_at", alias = "modified")]
+    updated: Option<String>,
+    daily: bool,
+}
+
+/// Conventions of the Markdown directory being imported.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MarkdownFlavor {
+    /// Metadata comes from front matter and folders only
+    Plain,
+    /// An Obsidian vault: inline `#tags` are tags too, date-named files are
+    /// daily notes, and a file's name is its title
+    Obsidian,
+}
+
+/// Notes read from a directory of Markdown files, plus the files that could
+/// not be used and why.
+pub struct MarkdownScan {
+    pub notes: Vec<Note>,
+    pub skipped: Vec<(PathBuf, String)>,
+}
+
+/// Walk `root` for `.md` files. Front matter supplies tags, category and
+/// timestamps; otherwise the file's modification time is used and its
+/// folder, relative to `root`, becomes the category. Hidden files and
+/// folders are ignored.
+pub fn parse_markdown_dir(root: &Path, flavor: MarkdownFlavor) -> Result<MarkdownScan> {
+    if !root.is_dir() {
+        bail!("{} is not a directory", root.display());
+    }
+
+    let mut files = Vec::new();
+    collect_markdown_files(root, &mut files)?;
+    files.sort();
+
+    let mut scan = MarkdownScan {
+        notes: Vec::new(),
+        skipped: Vec::new(),
+    };
+    for path in files {
+        match parse_markdown_file(root, &path, flavor) {
+            Ok(Some(n)) => scan.notes.push(n),
+            Ok(None) => scan.skipped.push((path, "empty".to_string())),
+            Err(e) => scan.skipped.push((path, format!("{:#}", e))),
+        }
+    }
+    Ok(scan)
+}
+
+/// Symlinks are skipped: one pointing back up the tree would never end.
+fn collect_markdown_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir).with_context(|| format!("cannot read {}", dir.display()))? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        let hidden = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with('.'));
+        if hidden || file_type.is_symlink() {
+            continue;
+        }
+        if file_type.is_dir() {
+            collect_markdown_files(&path, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|e| e == "md" || e == "markdown")
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn parse_markdown_file(root: &Path, path: &Path, flavor: MarkdownFlavor) -> Result<Option<Note>> {
+    let raw = fs::read_to_string(path)?;
+    let (front, body) = match split_front_matter(&raw) {
+        Some((yaml, body)) if !yaml.trim().is_empty() => (
+            serde_norway::from_str::<NoteFrontMatter>(yaml).context("invalid front matter")?,
+            body,
+        ),
+        Some((_, body)) => (NoteFrontMatter::default(), body),
+        None => (NoteFrontMatter::default(), raw.as_str()),
+    };
+
+    let mut content = body.trim().to_string();
+    if content.is_empty() {
+        return Ok(None);
+    }
+
+    let mut tags = front.tags;
+    let mut daily = front.daily;
+    let mut dated = None;
+    if flavor == MarkdownFlavor::Obsidian {
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+        dated = NaiveDate::parse_from_str(stem, "%Y-%m-%d").ok();
+        daily |= dated.is_some();
+
+        for tag in inline_hashtags(&content) {
+            if !tags.iter().any(|t| t.eq_ignore_ascii_case(&tag)) {
+                tags.push(tag);
+            }
+        }
+        content = strip_tag_line(&content).to_string();
+        if content.is_empty() {
+            return Ok(None);
+        }
+
+        // Obsidian titles a note by its file name; keep it as the first
+        // line unless the note already starts with it
+        let title = content
+            .lines()
+            .next()
+            .unwrap_or("")
+            .trim_start_matches('#')
+            .trim();
+        if front.id.is_none() && !daily && title != stem {
+            content = format!("# {}\n\n{}", stem, content);
+        }
+    }
+
+    let modified: DateTime<Local> = DateTime::from(fs::metadata(path)?.modified()?);
+    let created_at = match (front.created, dated) {
+        (Some(ref s), _) => parse_time(s)?,
+        (None, Some(date)) => parse_time(&date.to_string())?,
+        (None, None) => modified,
+    };
+    let updated_at = match front.updated {
+        Some(ref s) => parse_time(s)?,
+        None => modified.max(created_at),
+    };
+
+    // A daily note's folder only says that it is a daily note
+    let category = front.category.or_else(|| {
+        if dated.is_some() {
+            return None;
+        }
+        let folder = path.parent()?.strip_prefix(root).ok()?;
+        let parts: Vec<&str> = folder.iter().filter_map(|p| p.to_str()).collect();
+        (!parts.is_empty()).then(|| parts.join("/"))
+    });
+
+    Ok(Some(Note {
+        // 0 means "no original id"; the database assigns one
+        id: front.id.unwrap_or(0),
+        content,
+        created_at,
+        updated_at,
+        category,
+        is_daily: daily,
+        tags,
+    }))
+}
+
+/// `content` without a last line made only of hashtags, which the Obsidian
+/// export adds for a note's tags.
+fn strip_tag_line(content: &str) -> &str {
+    let (rest, last) = content.rsplit_once('\n').unwrap_or(("", content));
+    let words: Vec<&str> = last.split_whitespace().collect();
+    let only_tags = !words.is_empty()
+        && words.iter().all(|w| w.len() > 1 && w.starts_with('#') && !w[1..].contains('#'))
+        && inline_hashtags(last).len() == words.len();
+    if only_tags {
+        rest.trim_end()
+    } else {
+        content
+    }
+}
+
+/// Write `notes` to the database in a single transaction, which is rolled
+/// back on error or for a dry run.
+pub fn import_notes(conn: &Connection, notes: &[Note], opts: &ImportOptions) -> Result<ImportReport> {
+    let tx = conn.unchecked_transaction()?;
+    let mut report = ImportReport::default();
+
+    if opts.mode == Mode::Replace {
+        report.removed = tx.query_row("SELECT COUNT(*) FROM notes", [], |row| row.get(0))?;
+
+        // Remember which TODOs came from which note, so they re-attach to
+        // notes restored under the same id instead of being duplicated by
+        // the checkbox sync. Foreign keys are checked at commit, once the
+        // links that have no note any more are cleared.
+        let links: Vec<(i64, i64)> = tx
+            .prepare("SELECT id, note_id FROM todos WHERE note_id IS NOT NULL")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        tx.execute_batch(
+            "PRAGMA defer_foreign_keys = ON;
+             DELETE FROM notes_fts;
+             DELETE FROM tags;
+             DELETE FROM notes;",
+        )?;
+        for (todo_id, note_id) in links {
+            tx.execute(
+                "UPDATE todos SET note_id = ?1 WHERE id = ?2",
+                params![note_id, todo_id],
+            )?;
+        }
+    }
+
+    for (i, n) in notes.iter().enumerate() {
+        let has_id = n.id > 0;
+        let id_taken = has_id && note_exists(&tx, n.id)?;
+
+        if opts.mode == Mode::Merge {
+            let duplicate = match opts.matching {
+                Match::Id => id_taken,
+                Match::Content => tx
+                    .query_row(
+                        "SELECT COUNT(*) > 0 FROM notes WHERE content = ?1",
+                        params![n.content],
+                        |row| row.get(0),
+                    )?,
+            };
+            if duplicate {
+                report.skipped += 1;
+                continue;
+            }
+        } else if id_taken {
+            bail!("note id {} appears more than once in the import", n.id);
+        }
+
+        // Keep the original id whenever it is free, so links like `#12` in
+        // other notes still point at the right place
+        note::insert(&tx, n, has_id && !id_taken)?;
+        report.imported += 1;
+        report.inserted.push(i);
+    }
+
+    if opts.mode == Mode::Replace {
+        tx.execute(
+            "UPDATE todos SET note_id = NULL \
+             WHERE note_id IS NOT NULL AND note_id NOT IN (SELECT id FROM notes)",
+            [],
+        )?;
+    }
+
+    if opts.dry_run {
+        tx.rollback()?;
+    } else {
+        tx.commit()?;
+    }
+    Ok(report)
+}
+
+fn note_exists(conn: &Connection, id: i64) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) > 0 FROM notes WHERE id = ?1",
+        params![id],
+        |row| row.get(0),
+    )
+}
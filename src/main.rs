@@ -1,16 +1,32 @@
+mod agenda;
+mod backup;
+mod config;
+mod csvfile;
+mod daily;
 mod db;
 mod display;
+mod engine;
+mod enex;
 mod export;
+mod ics;
+mod import;
 mod note;
+mod org;
+mod periodic;
 mod search;
+mod site;
 mod tags;
 mod template;
+mod timelog;
 mod todo;
+mod todotxt;
 pub mod utils;
 
+use anyhow::Context;
 use clap::{Parser, Subcommand};
 use std::fs;
-use std::io::{self, Read};
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 /// Lightning-fast note-taking and task management CLI
@@ -23,6 +39,7 @@ Quick start:
   notectl todo add \"Review PR\" --priority high --due 2026-03-01
   notectl tags
   notectl daily
+  notectl agenda --days 7
   notectl export --format markdown --output notes.md
 ")]
 struct Cli {
@@ -119,7 +136,8 @@ Examples:
 
     /// Show or edit a specific note
     #[command(long_about = "\
-Display the full content of a note by its ID, including metadata.
+Display the full content of a note by its ID, including metadata and the
+TODOs extracted from its Markdown checkboxes.
 
 Examples:
   notectl show 1
@@ -160,12 +178,28 @@ Examples:
     /// Manage TODOs
     #[command(long_about = "\
 Create, list, complete, and delete TODO items with priorities and due dates.
+TODOs can be grouped by project and tagged like notes. Checkboxes (- [ ] task)
+written in notes become linked TODOs, and completing either side updates both;
+rewording a checkbox keeps its TODO, and removing one only unlinks it.
 TODOs are sorted by priority (high > medium > low) then by due date.
 
 Examples:
   notectl todo add \"Review PR\" --priority high --due 2026-03-01
+  notectl todo add \"Write tests\" --project notectl --tags rust,testing
   notectl todo list
   notectl todo list --pending
+  notectl todo list --project notectl --tag rust
+  notectl todo snooze 4 3d
+  notectl todo list --all
+  notectl todo archive --completed-before 30d
+  notectl todo block 4 --on 2
+  notectl todo next
+  notectl todo move 3 doing
+  notectl todo start 3
+  notectl todo stop
+  notectl todo time --since 1w --by project
+  notectl todo export --format todotxt --output todo.txt
+  notectl todo import todo.txt
   notectl todo done 3
   notectl todo delete 5")]
     Todo {
@@ -173,31 +207,135 @@ Examples:
         action: TodoAction,
     },
 
+    /// Show upcoming TODOs and daily notes by day
+    #[command(long_about = "\
+Show overdue TODOs followed by TODOs due today, tomorrow, and the coming
+days, interleaved with the daily notes written for those dates.
+
+Examples:
+  notectl agenda
+  notectl agenda --days 14
+  notectl agenda --calendar")]
+    Agenda {
+        /// Number of days to include, starting today (1-366)
+        #[arg(long, default_value = "7", value_parser = clap::value_parser!(u32).range(1..=366))]
+        days: u32,
+
+        /// Also render a week calendar grid
+        #[arg(long)]
+        calendar: bool,
+    },
+
     /// Open or show daily note
     #[command(long_about = "\
 Create or edit a daily note for today (or a specific date) using your $EDITOR.
-Daily notes include a structured template with Tasks, Notes, and Ideas sections.
+Daily notes include a structured template with Tasks, Notes, and Ideas sections,
+or use the template named by [daily] template in config.toml.
+
+Unchecked items from the previous daily note are carried over into a new one
+(set [daily] carry_over = false to disable). Carried items keep their TODO and
+are marked [>] in the old note. Custom templates receive them as carried_over:
+
+  {{#each carried_over}}- [ ] {{ this }}
+  {{/each}}
 
 Examples:
   notectl daily
   notectl daily --show
   notectl daily --date yesterday
+  notectl daily --date -2d
+  notectl daily --date \"last friday\"
   notectl daily --date 2026-02-14
-  notectl daily --date yesterday --show")]
+  notectl daily --date yesterday --show
+  notectl daily --template daily-review")]
     Daily {
         /// Show the daily note instead of opening editor
         #[arg(long)]
         show: bool,
 
-        /// Date (YYYY-MM-DD or "yesterday")
+        /// Date: YYYY-MM-DD, today, yesterday, -2d, +1w, friday, "last friday"
+        #[arg(long, allow_hyphen_values = true)]
+        date: Option<String>,
+
+        /// Template for a new daily note (overrides [daily] template)
+        #[arg(long)]
+        template: Option<String>,
+    },
+
+    /// Open or show this week's review note
+    #[command(long_about = "\
+Create or edit the review note for the current week (Monday to Sunday), or the
+week containing --date. The note opens with a generated summary: notes
+created, TODOs completed, top tags and links to the week's daily notes. The
+summary is refreshed every time the note is reopened; everything outside it
+is kept as written.
+
+Set [weekly] template in config.toml to use your own template. It receives
+summary (the refreshable block), stats, daily_notes and period.
+
+Examples:
+  notectl weekly
+  notectl weekly --show
+  notectl weekly --date -1w")]
+    Weekly {
+        /// Show the note instead of opening editor
+        #[arg(long)]
+        show: bool,
+
+        /// Any date in the week: YYYY-MM-DD, -1w, \"last friday\", ...
+        #[arg(long, allow_hyphen_values = true)]
+        date: Option<String>,
+
+        /// Template for a new note (overrides [weekly] template)
+        #[arg(long)]
+        template: Option<String>,
+    },
+
+    /// Open or show this month's review note
+    #[command(long_about = "\
+Create or edit the review note for the current month, or the month containing
+--date. Works like `notectl weekly`, with its own [monthly] template.
+
+Examples:
+  notectl monthly
+  notectl monthly --show
+  notectl monthly --date 2026-09-01")]
+    Monthly {
+        /// Show the note instead of opening editor
         #[arg(long)]
+        show: bool,
+
+        /// Any date in the month: YYYY-MM-DD, -30d, ...
+        #[arg(long, allow_hyphen_values = true)]
         date: Option<String>,
+
+        /// Template for a new note (overrides [monthly] template)
+        #[arg(long)]
+        template: Option<String>,
+    },
+
+    /// Show TODOs as a kanban board
+    #[command(long_about = "\
+Render TODOs in workflow columns side by side. Columns and WIP limits are
+read from the [board] section of ~/.notectl/config.toml:
+
+  [board]
+  columns = [\"todo\", \"doing\", \"waiting\", \"done\"]
+  wip_limits = { doing = 3 }
+
+Examples:
+  notectl board
+  notectl board --project notectl")]
+    Board {
+        /// Filter by project
+        #[arg(long)]
+        project: Option<String>,
     },
 
     /// Manage tags
     #[command(long_about = "\
-List all tags with note counts, show notes for a specific tag, or rename tags.
-Without arguments, displays all tags sorted by frequency.
+List all tags with note and TODO counts, show items for a specific tag, or
+rename tags. Without arguments, displays all tags sorted by frequency.
 
 Examples:
   notectl tags
@@ -205,7 +343,7 @@ Examples:
   notectl tags rename old-name new-name
   notectl tags --show daily")]
     Tags {
-        /// Show notes for a specific tag
+        /// Show notes and TODOs for a specific tag
         #[arg(long)]
         show: Option<String>,
 
@@ -218,12 +356,18 @@ Examples:
 Create, list, edit, and delete reusable note templates.
 Templates support variables like {title}, {date}, {time}, and {datetime}.
 
+Templates are stored in the database or as `<name>.md` files in
+~/.notectl/templates/ (configurable via [templates] dir in config.toml).
+A file template takes precedence over a database template of the same name.
+
 Examples:
   notectl template create standup --editor
   notectl template create bug --content \"## Bug Report\\n\\n**Steps**: ...\"
   notectl template list
   notectl template edit standup
-  notectl template delete old-template")]
+  notectl template delete old-template
+  notectl template export ./team-templates
+  notectl template import ./team-templates")]
     Template {
         #[command(subcommand)]
         action: TemplateAction,
@@ -234,10 +378,33 @@ Examples:
 Generate a new note from a saved template. Opens your $EDITOR with the
 rendered template for further editing before saving.
 
+Templates may start with a front matter block setting defaults for the note:
+
+  ---
+  tags: [meeting]
+  category: work
+  todo: true        # also add a follow-up TODO for the note
+  ---
+
+The --tags, --category, --todo and --no-todo flags override these defaults.
+
+Any {name} placeholder can be filled with --var name=value. Front matter can
+declare variables with a description and default:
+
+  vars:
+    - name: client
+      description: Client name
+      default: Acme
+
+Missing variables are prompted for when running in a terminal; otherwise
+their defaults are used, and any placeholder left unresolved is an error.
+
 Examples:
   notectl new --template standup
   notectl new --template meeting --title \"Q1 Planning\"
-  notectl new --template bug --title \"Login page crash\"")]
+  notectl new --template bug --title \"Login page crash\"
+  notectl new --template meeting --tags meeting,client --no-todo
+  notectl new --template retro --var client=Acme --var sprint=14")]
     New {
         /// Template name to use
         #[arg(long)]
@@ -246,6 +413,26 @@ Examples:
         /// Title variable for the template
         #[arg(long)]
         title: Option<String>,
+
+        /// Comma-separated tags (replaces the template's tags)
+        #[arg(long, value_delimiter = ',')]
+        tags: Option<Vec<String>>,
+
+        /// Category (replaces the template's category)
+        #[arg(long)]
+        category: Option<String>,
+
+        /// Add a follow-up TODO for the note
+        #[arg(long, conflicts_with = "no_todo")]
+        todo: bool,
+
+        /// Don't add a follow-up TODO, even if the template asks for one
+        #[arg(long)]
+        no_todo: bool,
+
+        /// Template variable as name=value (repeatable)
+        #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_var)]
+        vars: Vec<(String, String)>,
     },
 
     /// Export notes
@@ -253,14 +440,46 @@ Examples:
 Export notes to markdown or JSON format, with optional filters.
 Output goes to stdout by default, or to a file with --output.
 
+The ics format writes an iCalendar file with every TODO as a VTODO (--tag
+filters them); add --journal to include daily notes as VJOURNAL entries.
+UIDs are stable, so re-importing updates existing calendar entries.
+
+The md-dir format writes one Markdown file per note into the --output
+directory, with YAML front matter (id, created, updated, tags, category).
+Re-exporting into the same directory only rewrites notes that changed and
+removes the files of notes that were deleted or no longer match the filters.
+
+The obsidian format writes an Obsidian vault to the --output directory: daily
+notes go to Daily/YYYY-MM-DD.md, other notes to <category>/<title>.md, and
+tags are appended inline as #tags. [[links]] are kept as written.
+
+The html format writes a static site to the --output directory: an index with
+a search box, a page per tag and a page per note with its Markdown rendered.
+It needs no server; open index.html in a browser or publish the folder.
+
+The csv format writes one row per note for spreadsheets. Pick the columns
+with --columns (id, created, updated, title, content, tags, category, daily);
+multi-line notes are quoted so they stay in one cell.
+
+The org format writes an Emacs Org file: each note is a heading with its tags
+and a PROPERTIES drawer (ID, CREATED, UPDATED, CATEGORY), followed by every
+TODO (--tag filters them) as a TODO/DONE headline with DEADLINE/SCHEDULED and
+[#A]/[#C] priority cookies.
+
 Examples:
   notectl export
   notectl export --format json --output backup.json
   notectl export --format markdown --output notes.md
   notectl export --tag work --from 2026-01-01 --to 2026-01-31
-  notectl export --format json --tag meeting --output meetings.json")]
+  notectl export --format json --tag meeting --output meetings.json
+  notectl export --format ics --journal --output notectl.ics
+  notectl export --format md-dir --output ./vault
+  notectl export --format obsidian --output ~/Obsidian/notectl
+  notectl export --format html --tag handbook --output ./site
+  notectl export --format csv --columns created,title,tags --output notes.csv
+  notectl export --format org --output notes.org")]
     Export {
-        /// Output format: markdown, json
+        /// Output format: markdown, json, ics, md-dir, obsidian, html, csv, org
         #[arg(long, default_value = "markdown")]
         format: String,
 
@@ -279,6 +498,113 @@ Examples:
         /// End date (YYYY-MM-DD)
         #[arg(long)]
         to: Option<String>,
+
+        /// Include daily notes as VJOURNAL entries (ics only)
+        #[arg(long)]
+        journal: bool,
+
+        /// Comma-separated columns to write (csv only)
+        #[arg(long)]
+        columns: Option<String>,
+    },
+
+    /// Import notes
+    #[command(long_about = "\
+Import notes from a file written by `notectl export --format json`, keeping
+their original timestamps, category, tags and daily-note flag.
+
+With --format markdown, PATH is a directory walked for .md files. YAML front
+matter (tags, category, created, updated) is applied when present; otherwise
+the file's modification time is used, and subfolders become categories
+(notes/work/acme/x.md gets category work/acme when importing notes/).
+
+--format obsidian reads an Obsidian vault the same way, and also turns inline
+#hashtags into tags and date-named files (Daily/2026-02-14.md) into daily
+notes. A note that doesn't start with its file name gets it as a heading.
+
+--format csv reads a file with a header row. Columns named like the export's
+(id, created, updated, title, content, tags, category, daily) are picked up
+automatically; use --map field=Header for others, e.g. --map content=Body.
+
+--format org reads an Org file: headlines become notes (deeper headlines stay
+in the note as sub-headings) and TODO/DONE headlines become TODOs, with tags,
+properties, DEADLINE/SCHEDULED and [#A] priorities mapped back. TODOs already
+present with the same text and creation date are skipped.
+
+--format enex reads an Evernote export (.enex). Note bodies are converted to
+Markdown and keep their tags and created/updated times. Embedded images and
+files are saved under ~/.notectl/attachments/ (or [attachments] dir) and
+linked from the note; --skip-attachments leaves them out. Anything that can't
+be carried over, such as encrypted sections, is listed after the import.
+
+By default (--merge) existing notes are kept and notes already present are
+skipped: --match content skips notes whose content is identical to an existing
+note, --match id skips notes whose id is taken. --replace deletes every
+existing note first and restores the file exactly, ids included. Imported
+notes keep their original id whenever it is free.
+
+Use --dry-run to see what would happen without changing anything.
+
+Examples:
+  notectl import backup.json
+  notectl import backup.json --dry-run
+  notectl import backup.json --match id
+  notectl import backup.json --replace
+  notectl import ~/old-notes --format markdown --dry-run
+  notectl import ~/Obsidian/Work --format obsidian
+  notectl import notes.csv --format csv --map content=Body --map created=Date
+  notectl import ~/org/notes.org --format org
+  notectl import Evernote.enex --format enex")]
+    Import {
+        /// File (json, csv, org, enex) or directory (markdown, obsidian) to import
+        path: String,
+
+        /// Input format: json, markdown, obsidian, csv, org, enex
+        #[arg(long, default_value = "json")]
+        format: String,
+
+        /// Keep existing notes and add the missing ones (default)
+        #[arg(long, conflicts_with = "replace")]
+        merge: bool,
+
+        /// Delete all existing notes before importing
+        #[arg(long)]
+        replace: bool,
+
+        /// Duplicate detection for --merge: content, id
+        #[arg(long = "match", default_value = "content")]
+        match_by: String,
+
+        /// Report what would be imported without writing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Read a field from a differently named CSV column, as field=Header (repeatable)
+        #[arg(long = "map", value_name = "FIELD=HEADER", value_parser = parse_var)]
+        map: Vec<(String, String)>,
+
+        /// Don't save files embedded in an Evernote export
+        #[arg(long)]
+        skip_attachments: bool,
+    },
+
+    /// Back up and restore the database
+    #[command(long_about = "\
+Take consistent snapshots of the whole database (notes, TODOs, time entries,
+templates) with SQLite's online backup API, list them, and restore one.
+
+Snapshots go to ~/.notectl/backups/ unless a path is given; only the newest
+[backup] keep (default 10) are kept there. A snapshot is also taken
+automatically before a schema migration and before every restore.
+
+Examples:
+  notectl backup create
+  notectl backup create ~/Dropbox/notectl.db
+  notectl backup list
+  notectl backup restore notes-20260214-093000.db")]
+    Backup {
+        #[command(subcommand)]
+        action: BackupAction,
     },
 
     /// Show note statistics
@@ -310,6 +636,18 @@ enum TodoAction {
         /// Due date (YYYY-MM-DD)
         #[arg(long)]
         due: Option<String>,
+
+        /// Project the TODO belongs to
+        #[arg(long)]
+        project: Option<String>,
+
+        /// Comma-separated tags
+        #[arg(long, value_delimiter = ',')]
+        tags: Option<Vec<String>>,
+
+        /// Hide until this date (YYYY-MM-DD) or span from now (3d, 2w)
+        #[arg(long)]
+        start: Option<String>,
     },
 
     /// List TODOs
@@ -317,6 +655,22 @@ enum TodoAction {
         /// Show only pending TODOs
         #[arg(long)]
         pending: bool,
+
+        /// Include TODOs deferred to a later start date
+        #[arg(long)]
+        all: bool,
+
+        /// Show archived TODOs instead of current ones
+        #[arg(long)]
+        archived: bool,
+
+        /// Filter by project
+        #[arg(long)]
+        project: Option<String>,
+
+        /// Filter by tag
+        #[arg(long)]
+        tag: Option<String>,
     },
 
     /// Mark a TODO as done
@@ -326,9 +680,195 @@ enum TodoAction {
     },
 
     /// Delete a TODO
+    #[command(long_about = "\
+Delete a TODO with its tags and dependencies. A TODO with tracked time is
+refused unless --force is given, since its time entries are deleted too.
+
+Examples:
+  notectl todo delete 5
+  notectl todo delete 5 --force")]
     Delete {
         /// TODO ID
         id: i64,
+
+        /// Delete even if time was tracked on it
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Archive old completed TODOs
+    #[command(long_about = "\
+Archive completed and cancelled TODOs closed before a cutoff, given as a
+span back from now (30d, 8w) or a date (YYYY-MM-DD). Archived TODOs are
+hidden from `todo list` and left out of `stats`; browse them with
+`todo list --archived`.
+
+Examples:
+  notectl todo archive --completed-before 30d
+  notectl todo archive --completed-before 2026-01-01
+  notectl todo list --archived")]
+    Archive {
+        /// Archive TODOs completed before this point
+        #[arg(long)]
+        completed_before: String,
+    },
+
+    /// Defer a TODO until later
+    #[command(long_about = "\
+Hide a TODO from `todo list`, `todo next` and the board until a later date.
+The time is a span from now (4h, 3d, 2w) or a date (YYYY-MM-DD); day spans
+wake the TODO at the start of that day. Use `todo list --all` to see
+deferred TODOs, and `snooze <id> 0d` to bring one back now.
+
+Examples:
+  notectl todo snooze 3 3d
+  notectl todo snooze 3 2026-03-01
+  notectl todo snooze 3 0d")]
+    Snooze {
+        /// TODO ID
+        id: i64,
+
+        /// When the TODO becomes actionable again
+        until: String,
+    },
+
+    /// Move a TODO to a workflow status
+    #[command(long_about = "\
+Move a TODO to a workflow status: todo, doing, waiting, done, cancelled, or
+any extra column listed under [board] in ~/.notectl/config.toml. Moves that
+would exceed a configured WIP limit are refused unless --force is given.
+
+Examples:
+  notectl todo move 3 doing
+  notectl todo move 3 waiting
+  notectl todo move 3 doing --force")]
+    Move {
+        /// TODO ID
+        id: i64,
+
+        /// Target status
+        status: String,
+
+        /// Ignore WIP limits
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Start the timer on a TODO (stops any running timer)
+    Start {
+        /// TODO ID
+        id: i64,
+    },
+
+    /// Stop the running timer
+    Stop,
+
+    /// Report tracked time
+    #[command(long_about = "\
+Summarize time tracked with `todo start`/`todo stop`. The period is a span
+back from now (45m, 8h, 3d, 2w) or a start date (YYYY-MM-DD).
+
+Examples:
+  notectl todo time
+  notectl todo time --since 2w --by project
+  notectl todo time --since 2026-02-01 --by day
+  notectl todo time --by tag")]
+    Time {
+        /// Period to report on
+        #[arg(long, default_value = "1w")]
+        since: String,
+
+        /// Group by: project, task, tag, day
+        #[arg(long, default_value = "project")]
+        by: String,
+    },
+
+    /// Import TODOs from a file
+    #[command(long_about = "\
+Import TODOs from a todo.txt file. Priorities (A)/(B)/(C) map to
+high/medium/low, due: sets the due date, t: the start date, +project the
+project and @context the tags; completed lines (x 2026-02-01 ...) are
+imported as done. Tasks already present with the same text and creation
+date are skipped.
+
+With --format csv the file needs a header row. Columns named task, status,
+priority, due, start, created, completed, project and tags are read
+automatically; use --map field=Header for differently named ones. Only the
+task is required.
+
+With --format org, TODO/DONE headlines are imported with their priority
+cookie, tags, DEADLINE and SCHEDULED dates; other headlines are ignored.
+
+Examples:
+  notectl todo import todo.txt
+  notectl todo import ~/Dropbox/todo/done.txt --format todotxt
+  notectl todo import tasks.csv --format csv --map task=Title --map due=Deadline
+  notectl todo import ~/org/inbox.org --format org")]
+    Import {
+        /// File to import
+        file: String,
+
+        /// Input format: todotxt, csv, org
+        #[arg(long, default_value = "todotxt")]
+        format: String,
+
+        /// Read a field from a differently named CSV column, as field=Header (repeatable)
+        #[arg(long = "map", value_name = "FIELD=HEADER", value_parser = parse_var)]
+        map: Vec<(String, String)>,
+    },
+
+    /// Export TODOs
+    #[command(long_about = "\
+Export every TODO, including archived and deferred ones.
+
+The csv format writes one row per TODO. Pick the columns with --columns
+(id, task, status, priority, due, start, created, completed, project, tags,
+note, blocked_by, tracked_minutes). The org format writes TODO/DONE headlines.
+
+Examples:
+  notectl todo export --output todo.txt
+  notectl todo export --format csv --output todos.csv
+  notectl todo export --format csv --columns task,status,due,tracked_minutes
+  notectl todo export --format org --output todos.org")]
+    Export {
+        /// Output format: todotxt, csv, org
+        #[arg(long, default_value = "todotxt")]
+        format: String,
+
+        /// Output file path
+        #[arg(long)]
+        output: Option<String>,
+
+        /// Comma-separated columns to write (csv only)
+        #[arg(long)]
+        columns: Option<String>,
+    },
+
+    /// Mark a TODO as blocked by another
+    Block {
+        /// TODO ID that is blocked
+        id: i64,
+
+        /// TODO ID it waits on
+        #[arg(long)]
+        on: i64,
+    },
+
+    /// Remove a blocking dependency
+    Unblock {
+        /// TODO ID that is blocked
+        id: i64,
+
+        /// TODO ID it no longer waits on
+        #[arg(long)]
+        on: i64,
+    },
+
+    /// Show actionable TODOs (pending and unblocked)
+    Next {
+        /// Maximum number of TODOs to show
+        #[arg(long, default_value = "5")]
+        limit: usize,
     },
 }
 
@@ -343,6 +883,24 @@ enum TagAction {
     },
 }
 
+#[derive(Subcommand)]
+enum BackupAction {
+    /// Snapshot the database
+    Create {
+        /// Destination file (default: a timestamped file in the backup directory)
+        path: Option<PathBuf>,
+    },
+
+    /// List snapshots in the backup directory
+    List,
+
+    /// Replace the database with a snapshot
+    Restore {
+        /// Snapshot file name (from `backup list`) or path
+        backup: String,
+    },
+}
+
 #[derive(Subcommand)]
 enum TemplateAction {
     /// Create a new template
@@ -373,6 +931,18 @@ enum TemplateAction {
         /// Template name
         name: String,
     },
+
+    /// Write all templates to a directory as .md files
+    Export {
+        /// Destination directory
+        dir: PathBuf,
+    },
+
+    /// Store every .md file in a directory as a template
+    Import {
+        /// Source directory
+        dir: PathBuf,
+    },
 }
 
 fn get_editor() -> String {
@@ -381,53 +951,224 @@ fn get_editor() -> String {
         .unwrap_or_else(|_| "vi".to_string())
 }
 
-fn edit_with_editor(initial_content: &str) -> io::Result<String> {
-    let tmp_dir = std::env::temp_dir();
-    let tmp_file = tmp_dir.join(format!("notectl_{}.md", std::process::id()));
-
-    fs::write(&tmp_file, initial_content)?;
-
-    let editor = get_editor();
-    let status = Command::new(&editor).arg(&tmp_file).status()?;
-
-    if !status.success() {
-        fs::remove_file(&tmp_file).ok();
-        return Err(io::Error::new(
-            io::ErrorKind::Other,
-            "Editor exited with non-zero status",
-        ));
+fn parse_var(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!("expected NAME=VALUE, got '{}'", s)),
     }
+}
 
-    let content = fs::read_to_string(&tmp_file)?;
-    fs::remove_file(&tmp_file).ok();
-    Ok(content)
+/// Value for a template variable: prompted for on a terminal, falling back to
+/// the declared default.
+fn resolve_var(
+    name: &str,
+    spec: Option<&template::VarSpec>,
+    interactive: bool,
+) -> Option<String> {
+    let default = spec.and_then(|s| s.default.clone());
+    if !interactive {
+        return default;
+    }
+    match prompt_var(name, spec) {
+        Ok(v) if v.is_empty() => default,
+        Ok(v) => Some(v),
+        Err(e) => {
+            display::print_error(&format!("Failed to read input: {}", e));
+            std::process::exit(1);
+        }
+    }
 }
 
-fn main() {
-    let cli = Cli::parse();
+fn load_template(
+    conn: &rusqlite::Connection,
+    dir: &std::path::Path,
+    name: &str,
+) -> template::Template {
+    match template::get(conn, dir, name) {
+        Ok(Some(t)) => t,
+        Ok(None) => {
+            display::print_error(&format!("Template '{}' not found", name));
+            std::process::exit(1);
+        }
+        Err(e) => {
+            display::print_error(&format!("Failed to get template: {}", e));
+            std::process::exit(1);
+        }
+    }
+}
 
-    let conn = match db::open_connection() {
-        Ok(c) => c,
+fn template_parts(tmpl: &template::Template) -> (template::FrontMatter, &str) {
+    match tmpl.parts() {
+        Ok(parts) => parts,
         Err(e) => {
-            display::print_error(&format!("Failed to open database: {}", e));
+            display::print_error(&format!(
+                "Invalid front matter in template '{}': {}",
+                tmpl.name, e
+            ));
             std::process::exit(1);
         }
-    };
+    }
+}
 
-    if let Err(e) = db::initialize(&conn) {
-        display::print_error(&format!("Failed to initialize database: {}", e));
-        std::process::exit(1);
+/// Render a template body, prompting for declared and undeclared variables
+/// on a terminal. Exits with an error listing any placeholder left unresolved.
+fn fill_template(
+    conn: &rusqlite::Connection,
+    body: &str,
+    declared: &[template::VarSpec],
+    mut vars: Vec<(String, String)>,
+    extra: serde_json::Map<String, serde_json::Value>,
+) -> String {
+    let interactive = io::stdin().is_terminal();
+    for spec in declared {
+        if vars.iter().any(|(n, _)| *n == spec.name) {
+            continue;
+        }
+        if let Some(value) = resolve_var(&spec.name, Some(spec), interactive) {
+            vars.push((spec.name.clone(), value));
+        }
     }
 
-    match cli.command {
-        Commands::Add {
-            content,
-            tags,
-            category,
-            stdin,
-        } => cmd_add(&conn, content, tags, category, stdin),
+    let mut rendered = render_template(conn, body, &vars, &extra);
 
-        Commands::List {
+    // Prompt for placeholders the template uses without declaring them
+    if interactive && !rendered.missing.is_empty() {
+        for name in &rendered.missing {
+            if let Some(value) = resolve_var(name, None, true) {
+                vars.push((name.clone(), value));
+            }
+        }
+        rendered = render_template(conn, body, &vars, &extra);
+    }
+
+    if !rendered.missing.is_empty() {
+        let names: Vec<String> = rendered
+            .missing
+            .iter()
+            .map(|n| format!("{{{}}}", n))
+            .collect();
+        display::print_error(&format!(
+            "Unresolved template variables: {} (pass them with --var name=value)",
+            names.join(", ")
+        ));
+        std::process::exit(1);
+    }
+    rendered.text
+}
+
+fn render_template(
+    conn: &rusqlite::Connection,
+    body: &str,
+    vars: &[(String, String)],
+    extra: &serde_json::Map<String, serde_json::Value>,
+) -> engine::Rendered {
+    let var_refs: Vec<(&str, &str)> = vars
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    match template::render(conn, body, &var_refs, extra.clone()) {
+        Ok(r) => r,
+        Err(e) => {
+            display::print_error(&format!("Failed to render template: {}", e));
+            std::process::exit(1);
+        }
+    }
+}
+
+fn prompt_var(name: &str, spec: Option<&template::VarSpec>) -> io::Result<String> {
+    use colored::Colorize;
+    let mut prompt = name.cyan().to_string();
+    if let Some(description) = spec.and_then(|s| s.description.as_deref()) {
+        prompt.push_str(&format!(" ({})", description));
+    }
+    if let Some(default) = spec.and_then(|s| s.default.as_deref()) {
+        prompt.push_str(&format!(" [{}]", default.dimmed()));
+    }
+    print!("{}: ", prompt);
+    io::stdout().flush()?;
+
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    Ok(line.trim().to_string())
+}
+
+fn edit_with_editor(initial_content: &str) -> io::Result<String> {
+    let tmp_dir = std::env::temp_dir();
+    let tmp_file = tmp_dir.join(format!("notectl_{}.md", std::process::id()));
+
+    fs::write(&tmp_file, initial_content)?;
+
+    let editor = get_editor();
+    let status = Command::new(&editor).arg(&tmp_file).status()?;
+
+    if !status.success() {
+        fs::remove_file(&tmp_file).ok();
+        return Err(io::Error::other("Editor exited with non-zero status"));
+    }
+
+    let content = fs::read_to_string(&tmp_file)?;
+    fs::remove_file(&tmp_file).ok();
+    Ok(content)
+}
+
+fn load_config() -> config::Config {
+    match config::load() {
+        Ok(c) => c,
+        Err(e) => {
+            display::print_error(&format!(
+                "Invalid {}: {}",
+                config::get_config_path().display(),
+                e
+            ));
+            std::process::exit(1);
+        }
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    let mut conn = match db::open_connection() {
+        Ok(c) => c,
+        Err(e) => {
+            display::print_error(&format!("Failed to open database: {}", e));
+            std::process::exit(1);
+        }
+    };
+
+    // Snapshot older databases before initialize() changes their schema
+    match db::needs_migration(&conn) {
+        Ok(false) => {}
+        Ok(true) => {
+            use colored::Colorize;
+            let path = auto_backup(&conn, "pre-migration");
+            eprintln!(
+                "{}",
+                format!("Upgrading database; previous version saved to {}", path.display()).dimmed()
+            );
+        }
+        Err(e) => {
+            display::print_error(&format!("Failed to read database version: {}", e));
+            std::process::exit(1);
+        }
+    }
+
+    if let Err(e) = db::initialize(&conn) {
+        display::print_error(&format!("Failed to initialize database: {}", e));
+        std::process::exit(1);
+    }
+
+    match cli.command {
+        Commands::Add {
+            content,
+            tags,
+            category,
+            stdin,
+        } => cmd_add(&conn, content, tags, category, stdin),
+
+        Commands::List {
             today,
             tag,
             category,
@@ -447,13 +1188,50 @@ fn main() {
 
         Commands::Todo { action } => cmd_todo(&conn, action),
 
-        Commands::Daily { show, date } => cmd_daily(&conn, show, date),
+        Commands::Agenda { days, calendar } => cmd_agenda(&conn, days, calendar),
+
+        Commands::Board { project } => cmd_board(&conn, project),
+
+        Commands::Daily {
+            show,
+            date,
+            template,
+        } => cmd_daily(&conn, show, date, template),
+
+        Commands::Weekly {
+            show,
+            date,
+            template,
+        } => cmd_periodic(&conn, periodic::Period::Week, show, date, template),
+
+        Commands::Monthly {
+            show,
+            date,
+            template,
+        } => cmd_periodic(&conn, periodic::Period::Month, show, date, template),
 
         Commands::Tags { show, action } => cmd_tags(&conn, show, action),
 
         Commands::Template { action } => cmd_template(&conn, action),
 
-        Commands::New { template, title } => cmd_new(&conn, template, title),
+        Commands::New {
+            template,
+            title,
+            tags,
+            category,
+            todo,
+            no_todo,
+            vars,
+        } => {
+            let todo = if todo {
+                Some(true)
+            } else if no_todo {
+                Some(false)
+            } else {
+                None
+            };
+            cmd_new(&conn, template, title, vars, tags, category, todo)
+        }
 
         Commands::Export {
             format,
@@ -461,7 +1239,22 @@ fn main() {
             tag,
             from,
             to,
-        } => cmd_export(&conn, format, output, tag, from, to),
+            journal,
+            columns,
+        } => cmd_export(&conn, format, output, tag, from, to, journal, columns),
+
+        Commands::Import {
+            path,
+            format,
+            merge: _,
+            replace,
+            match_by,
+            dry_run,
+            map,
+            skip_attachments,
+        } => cmd_import(&conn, path, format, replace, match_by, dry_run, map, skip_attachments),
+
+        Commands::Backup { action } => cmd_backup(&mut conn, action),
 
         Commands::Stats { tags } => cmd_stats(&conn, tags),
     }
@@ -565,6 +1358,13 @@ fn cmd_show(conn: &rusqlite::Connection, id: i64) {
             if !n.tags.is_empty() {
                 println!("{} {}", "Tags:".dimmed(), n.tags.join(", "));
             }
+            match todo::list_for_note(conn, id) {
+                Ok(todos) => display::print_linked_todos(&todos),
+                Err(e) => {
+                    display::print_error(&format!("Failed to get linked TODOs: {}", e));
+                    std::process::exit(1);
+                }
+            }
         }
         Ok(None) => {
             display::print_error(&format!("Note {} not found", id));
@@ -639,6 +1439,9 @@ fn cmd_todo(conn: &rusqlite::Connection, action: TodoAction) {
             task,
             priority,
             due,
+            project,
+            tags,
+            start,
         } => {
             let prio = match priority.to_lowercase().as_str() {
                 "high" | "h" => "high",
@@ -646,7 +1449,26 @@ fn cmd_todo(conn: &rusqlite::Connection, action: TodoAction) {
                 _ => "medium",
             };
 
-            match todo::add(conn, &task, prio, due.as_deref()) {
+            let tag_list = tags.unwrap_or_default();
+
+            let defer_until = match start.as_deref().map(utils::parse_until) {
+                Some(Some(ts)) => Some(ts),
+                Some(None) => {
+                    display::print_error("Invalid --start. Use YYYY-MM-DD or a span like 3d");
+                    std::process::exit(1);
+                }
+                None => None,
+            };
+
+            match todo::add(
+                conn,
+                &task,
+                prio,
+                due.as_deref(),
+                project.as_deref(),
+                &tag_list,
+                defer_until,
+            ) {
                 Ok(id) => display::print_todo_added(id, &task),
                 Err(e) => {
                     display::print_error(&format!("Failed to add TODO: {}", e));
@@ -655,10 +1477,24 @@ fn cmd_todo(conn: &rusqlite::Connection, action: TodoAction) {
             }
         }
 
-        TodoAction::List { pending } => {
-            match todo::list_todos(conn, pending) {
+        TodoAction::List {
+            pending,
+            all,
+            archived,
+            project,
+            tag,
+        } => {
+            let filter = todo::TodoFilter {
+                pending_only: pending,
+                project: project.as_deref(),
+                tag: tag.as_deref(),
+                include_deferred: all || archived,
+                archived: Some(archived),
+            };
+            match todo::list_todos(conn, &filter) {
+                Ok(todos) if archived => display::print_todos_table(&todos, "Archived TODOs"),
                 Ok(todos) => {
-                    display::print_todos_table(&todos);
+                    display::print_todos_table(&todos, "Active TODOs");
                     if let (Ok(overdue), Ok(due_today)) =
                         (todo::count_overdue(conn), todo::count_due_today(conn))
                     {
@@ -684,88 +1520,705 @@ fn cmd_todo(conn: &rusqlite::Connection, action: TodoAction) {
             }
         },
 
-        TodoAction::Delete { id } => match todo::delete(conn, id) {
+        TodoAction::Delete { id, force } => {
+            match timelog::tracked(conn, id) {
+                Ok(secs) if secs > 0 && !force => {
+                    display::print_error(&format!(
+                        "TODO {} has {} of tracked time. Use --force to delete it with its time entries",
+                        id,
+                        utils::format_duration(secs)
+                    ));
+                    std::process::exit(1);
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    display::print_error(&format!("Failed to delete TODO: {}", e));
+                    std::process::exit(1);
+                }
+            }
+            match todo::delete(conn, id) {
+                Ok(true) => {
+                    use colored::Colorize;
+                    println!("{} TODO {} deleted", "✓".green().bold(), id.to_string().cyan());
+                }
+                Ok(false) => {
+                    display::print_error(&format!("TODO {} not found", id));
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    display::print_error(&format!("Failed to delete TODO: {}", e));
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        TodoAction::Archive { completed_before } => {
+            use chrono::{Local, NaiveDate};
+
+            let cutoff = if let Some(dt) = utils::parse_duration(&completed_before)
+                .and_then(|span| Local::now().checked_sub_signed(span))
+            {
+                dt.timestamp()
+            } else if let Ok(date) = NaiveDate::parse_from_str(&completed_before, "%Y-%m-%d") {
+                utils::day_bounds(date).0
+            } else {
+                display::print_error(
+                    "Invalid --completed-before. Use a span like 30d or YYYY-MM-DD",
+                );
+                std::process::exit(1);
+            };
+
+            match todo::archive_completed_before(conn, cutoff) {
+                Ok(count) => {
+                    use colored::Colorize;
+                    println!(
+                        "{} Archived {} TODO{}",
+                        "✓".green().bold(),
+                        count.to_string().cyan(),
+                        if count == 1 { "" } else { "s" }
+                    );
+                }
+                Err(e) => {
+                    display::print_error(&format!("Failed to archive TODOs: {}", e));
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        TodoAction::Snooze { id, until } => {
+            let ts = match utils::parse_until(&until) {
+                Some(ts) => ts,
+                None => {
+                    display::print_error("Invalid time. Use YYYY-MM-DD or a span like 3d");
+                    std::process::exit(1);
+                }
+            };
+
+            match todo::snooze(conn, id, ts) {
+                Ok(true) => {
+                    use colored::Colorize;
+                    println!(
+                        "{} TODO {} snoozed until {}",
+                        "✓".green().bold(),
+                        id.to_string().cyan(),
+                        utils::timestamp_to_local(ts).format("%Y-%m-%d %H:%M")
+                    );
+                }
+                Ok(false) => {
+                    display::print_error(&format!("TODO {} not found", id));
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    display::print_error(&format!("Failed to snooze TODO: {}", e));
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        TodoAction::Move { id, status, force } => {
+            let cfg = load_config();
+            let status = status.to_lowercase();
+
+            if !todo::STATUSES.contains(&status.as_str()) && !cfg.board.columns.contains(&status) {
+                display::print_error(&format!(
+                    "Unknown status '{}'. Use one of: {}",
+                    status,
+                    todo::STATUSES.join(", ")
+                ));
+                std::process::exit(1);
+            }
+
+            if let Some(&limit) = cfg.board.wip_limits.get(&status) {
+                let current = match todo::get_by_id(conn, id) {
+                    Ok(Some(t)) => t,
+                    Ok(None) => {
+                        display::print_error(&format!("TODO {} not found", id));
+                        std::process::exit(1);
+                    }
+                    Err(e) => {
+                        display::print_error(&format!("Failed to get TODO: {}", e));
+                        std::process::exit(1);
+                    }
+                };
+                let count = todo::count_in_status(conn, &status).unwrap_or(0) as usize;
+                if current.status != status && count >= limit && !force {
+                    display::print_error(&format!(
+                        "WIP limit reached for '{}' ({}/{}). Finish something first or use --force",
+                        status, count, limit
+                    ));
+                    std::process::exit(1);
+                }
+            }
+
+            match todo::set_status(conn, id, &status) {
+                Ok(true) => {
+                    use colored::Colorize;
+                    println!(
+                        "{} TODO {} moved to {}",
+                        "✓".green().bold(),
+                        id.to_string().cyan(),
+                        status.bold()
+                    );
+                }
+                Ok(false) => {
+                    display::print_error(&format!("TODO {} not found", id));
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    display::print_error(&format!("Failed to move TODO: {}", e));
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        TodoAction::Start { id } => match timelog::start(conn, id) {
+            Ok(timelog::StartOutcome::Started { previous }) => {
+                use colored::Colorize;
+                if let Some(prev) = previous {
+                    println!(
+                        "{} Stopped timer on TODO {} ({})",
+                        "✓".green().bold(),
+                        prev.todo_id.to_string().cyan(),
+                        utils::format_duration(prev.seconds)
+                    );
+                }
+                println!(
+                    "{} Timer started on TODO {}",
+                    "✓".green().bold(),
+                    id.to_string().cyan()
+                );
+            }
+            Ok(timelog::StartOutcome::AlreadyRunning) => {
+                display::print_error(&format!("Timer is already running on TODO {}", id));
+                std::process::exit(1);
+            }
+            Ok(timelog::StartOutcome::NotFound) => {
+                display::print_error(&format!("TODO {} not found", id));
+                std::process::exit(1);
+            }
+            Err(e) => {
+                display::print_error(&format!("Failed to start timer: {}", e));
+                std::process::exit(1);
+            }
+        },
+
+        TodoAction::Stop => match timelog::stop(conn) {
+            Ok(Some(stopped)) => {
+                use colored::Colorize;
+                println!(
+                    "{} Timer stopped on TODO {} ({})",
+                    "✓".green().bold(),
+                    stopped.todo_id.to_string().cyan(),
+                    utils::format_duration(stopped.seconds)
+                );
+                println!("  \"{}\"", stopped.task);
+            }
+            Ok(None) => {
+                display::print_error("No timer is running");
+                std::process::exit(1);
+            }
+            Err(e) => {
+                display::print_error(&format!("Failed to stop timer: {}", e));
+                std::process::exit(1);
+            }
+        },
+
+        TodoAction::Time { since, by } => {
+            use chrono::{Local, NaiveDate};
+
+            let since_ts = if let Some(dt) = utils::parse_duration(&since)
+                .and_then(|span| Local::now().checked_sub_signed(span))
+            {
+                dt.timestamp()
+            } else if let Ok(date) = NaiveDate::parse_from_str(&since, "%Y-%m-%d") {
+                utils::day_bounds(date).0
+            } else {
+                display::print_error("Invalid --since. Use a span like 1w or 3d, or YYYY-MM-DD");
+                std::process::exit(1);
+            };
+
+            let group = match timelog::GroupBy::parse(&by) {
+                Some(g) => g,
+                None => {
+                    display::print_error("Invalid --by. Use project, task, tag, or day");
+                    std::process::exit(1);
+                }
+            };
+
+            match (
+                timelog::report(conn, since_ts, group),
+                timelog::total(conn, since_ts),
+            ) {
+                (Ok(rows), Ok(total)) => {
+                    let running = timelog::running(conn).ok().flatten();
+                    display::print_time_report(
+                        &rows,
+                        total,
+                        &format!("Time tracked since {} by {}", since, by),
+                        running.as_ref(),
+                    );
+                }
+                (Err(e), _) | (_, Err(e)) => {
+                    display::print_error(&format!("Failed to build time report: {}", e));
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        TodoAction::Import { file, format, map } => {
+            if !matches!(format.as_str(), "todotxt" | "csv" | "org") {
+                display::print_error(&format!("Unsupported import format '{}'. Use todotxt, csv or org", format));
+                std::process::exit(1);
+            }
+
+            let content = match fs::read_to_string(&file) {
+                Ok(c) => c,
+                Err(e) => {
+                    display::print_error(&format!("Failed to read {}: {}", file, e));
+                    std::process::exit(1);
+                }
+            };
+
+            let imported = if format == "csv" {
+                csvfile::import_todos(conn, &content, &map).map(|r| (r.imported, r.skipped))
+            } else if format == "org" {
+                let mut todos = org::parse(&content).todos;
+                // Without the notes, checkbox TODOs are imported on their own
+                for t in &mut todos {
+                    t.note_id = None;
+                }
+                todo::import_deduped(conn, &todos, false)
+                    .map(|r| (r.imported, r.skipped))
+                    .map_err(anyhow::Error::from)
+            } else {
+                todotxt::import(conn, &content)
+                    .map(|r| (r.imported, r.skipped))
+                    .map_err(anyhow::Error::from)
+            };
+
+            match imported {
+                Ok((imported, skipped)) => {
+                    use colored::Colorize;
+                    println!(
+                        "{} Imported {} TODO{} from {} ({} skipped as duplicates)",
+                        "✓".green().bold(),
+                        imported.to_string().cyan(),
+                        if imported == 1 { "" } else { "s" },
+                        file,
+                        skipped
+                    );
+                }
+                Err(e) => {
+                    display::print_error(&format!("Import failed: {:#}", e));
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        TodoAction::Export { format, output, columns } => {
+            if columns.is_some() && format != "csv" {
+                display::print_error("--columns only applies to --format csv");
+                std::process::exit(1);
+            }
+            let content = match format.as_str() {
+                "todotxt" => todotxt::export(conn).map_err(anyhow::Error::from),
+                "csv" => csvfile::write_todos(conn, columns.as_deref()),
+                "org" => org::export(conn, None, &[]).map_err(anyhow::Error::from),
+                _ => {
+                    display::print_error(&format!("Unsupported export format '{}'. Use todotxt, csv or org", format));
+                    std::process::exit(1);
+                }
+            };
+
+            match content {
+                Ok(content) => write_output(output, &content),
+                Err(e) => {
+                    display::print_error(&format!("Export failed: {:#}", e));
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        TodoAction::Block { id, on } => match todo::block(conn, id, on) {
+            Ok(todo::BlockOutcome::Blocked) => {
+                use colored::Colorize;
+                println!(
+                    "{} TODO {} is blocked by {}",
+                    "✓".green().bold(),
+                    id.to_string().cyan(),
+                    on.to_string().cyan()
+                );
+            }
+            Ok(todo::BlockOutcome::NotFound(missing)) => {
+                display::print_error(&format!("TODO {} not found", missing));
+                std::process::exit(1);
+            }
+            Ok(todo::BlockOutcome::Cycle) => {
+                display::print_error(&format!(
+                    "TODO {} already depends on {}; blocking would create a cycle",
+                    on, id
+                ));
+                std::process::exit(1);
+            }
+            Err(e) => {
+                display::print_error(&format!("Failed to block TODO: {}", e));
+                std::process::exit(1);
+            }
+        },
+
+        TodoAction::Unblock { id, on } => match todo::unblock(conn, id, on) {
             Ok(true) => {
                 use colored::Colorize;
-                println!("{} TODO {} deleted", "✓".green().bold(), id.to_string().cyan());
+                println!(
+                    "{} TODO {} no longer blocked by {}",
+                    "✓".green().bold(),
+                    id.to_string().cyan(),
+                    on.to_string().cyan()
+                );
             }
             Ok(false) => {
-                display::print_error(&format!("TODO {} not found", id));
+                display::print_error(&format!("TODO {} is not blocked by {}", id, on));
                 std::process::exit(1);
             }
             Err(e) => {
-                display::print_error(&format!("Failed to delete TODO: {}", e));
+                display::print_error(&format!("Failed to unblock TODO: {}", e));
                 std::process::exit(1);
             }
         },
+
+        TodoAction::Next { limit } => match todo::next_actions(conn, limit) {
+            Ok(todos) => display::print_todos_table(&todos, "Next Actions"),
+            Err(e) => {
+                display::print_error(&format!("Failed to list next actions: {}", e));
+                std::process::exit(1);
+            }
+        },
+    }
+}
+
+fn cmd_agenda(conn: &rusqlite::Connection, days: u32, calendar: bool) {
+    match agenda::build(conn, days) {
+        Ok(a) => {
+            if calendar {
+                display::print_agenda_calendar(&a);
+                println!();
+            }
+            display::print_agenda(&a);
+        }
+        Err(e) => {
+            display::print_error(&format!("Failed to build agenda: {}", e));
+            std::process::exit(1);
+        }
     }
 }
 
-fn cmd_daily(conn: &rusqlite::Connection, show: bool, date: Option<String>) {
-    use chrono::{Duration, Local, NaiveDate};
+fn cmd_board(conn: &rusqlite::Connection, project: Option<String>) {
+    let cfg = load_config();
+
+    match todo::board(conn, &cfg.board.columns, project.as_deref()) {
+        Ok(columns) => display::print_board(&columns, &cfg.board.wip_limits),
+        Err(e) => {
+            display::print_error(&format!("Failed to load board: {}", e));
+            std::process::exit(1);
+        }
+    }
+}
 
+fn cmd_daily(
+    conn: &rusqlite::Connection,
+    show: bool,
+    date: Option<String>,
+    template_name: Option<String>,
+) {
+    use chrono::Local;
+
+    let today = Local::now().date_naive();
     let target_date = match date.as_deref() {
-        Some("yesterday") => Local::now().date_naive() - Duration::days(1),
-        Some(d) => match NaiveDate::parse_from_str(d, "%Y-%m-%d") {
-            Ok(nd) => nd,
-            Err(_) => {
-                display::print_error("Invalid date format. Use YYYY-MM-DD or 'yesterday'");
+        Some(d) => match utils::parse_date_expr(d, today) {
+            Some(nd) => nd,
+            None => {
+                display::print_error(
+                    "Invalid date. Use YYYY-MM-DD, yesterday, -2d, friday or 'last friday'",
+                );
                 std::process::exit(1);
             }
         },
-        None => Local::now().date_naive(),
+        None => today,
     };
 
-    let daily_title = format!("# Daily Note - {}\n", target_date);
-
-    // Check if daily note already exists for this date
-    let start_ts = target_date
-        .and_hms_opt(0, 0, 0)
-        .unwrap()
-        .and_local_timezone(Local)
-        .unwrap()
-        .timestamp();
-    let end_ts = target_date
-        .and_hms_opt(23, 59, 59)
-        .unwrap()
-        .and_local_timezone(Local)
-        .unwrap()
-        .timestamp();
-
-    let existing: Option<(i64, String)> = conn
-        .prepare("SELECT id, content FROM notes WHERE is_daily = 1 AND created_at >= ?1 AND created_at <= ?2 LIMIT 1")
-        .and_then(|mut stmt| {
-            let mut rows = stmt.query(rusqlite::params![start_ts, end_ts])?;
-            if let Some(row) = rows.next()? {
-                Ok(Some((row.get(0)?, row.get(1)?)))
-            } else {
-                Ok(None)
+    let existing = match daily::find(conn, target_date) {
+        Ok(n) => n,
+        Err(e) => {
+            display::print_error(&format!("Failed to load daily note: {}", e));
+            std::process::exit(1);
+        }
+    };
+
+    if show {
+        match existing {
+            Some(n) => {
+                use colored::Colorize;
+                println!("{} Daily Note #{} ({})\n", "---".dimmed(), n.id, target_date);
+                println!("{}", n.content);
             }
-        })
-        .unwrap_or(None);
+            None => {
+                display::print_error(&format!("No daily note found for {}", target_date));
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(ref n) = existing {
+        match edit_with_editor(&n.content) {
+            Ok(new_content) => {
+                let trimmed = new_content.trim().to_string();
+                if trimmed.is_empty() {
+                    display::print_error("Daily note cannot be empty");
+                    std::process::exit(1);
+                }
+                match note::update(conn, n.id, &trimmed) {
+                    Ok(_) => {
+                        use colored::Colorize;
+                        println!("{} Daily note updated ({})", "✓".green().bold(), target_date);
+                    }
+                    Err(e) => {
+                        display::print_error(&format!("Failed to update daily note: {}", e));
+                        std::process::exit(1);
+                    }
+                }
+            }
+            Err(e) => {
+                display::print_error(&format!("Editor error: {}", e));
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let cfg = load_config();
+    let previous = if cfg.daily.carry_over {
+        match daily::previous(conn, target_date) {
+            Ok(n) => n,
+            Err(e) => {
+                display::print_error(&format!("Failed to load previous daily note: {}", e));
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+    let carried = previous.as_ref().map(daily::open_items).unwrap_or_default();
+
+    let mut daily_tags = vec!["daily".to_string()];
+    let mut category = None;
+    let initial = match template_name.or(cfg.daily.template.clone()) {
+        Some(name) => {
+            let tmpl = load_template(conn, &cfg.templates_dir(), &name);
+            let (front, body) = template_parts(&tmpl);
+            for tag in front.tags {
+                if !daily_tags.contains(&tag) {
+                    daily_tags.push(tag);
+                }
+            }
+            category = front.category;
+
+            let vars = vec![("date".to_string(), target_date.to_string())];
+            let mut extra = serde_json::Map::new();
+            extra.insert("carried_over".into(), serde_json::json!(carried));
+            fill_template(conn, body, &front.vars, vars, extra)
+        }
+        None => {
+            let mut tasks = String::new();
+            for task in &carried {
+                tasks.push_str(&format!("- [ ] {}\n", task));
+            }
+            format!(
+                "# Daily Note - {}\n\n## Tasks\n{}- [ ] \n\n## Notes\n- \n\n## Ideas\n- \n\n---\nTags: #daily\n",
+                target_date, tasks
+            )
+        }
+    };
+
+    match edit_with_editor(&initial) {
+        Ok(new_content) => {
+            let trimmed = new_content.trim().to_string();
+            if trimmed.is_empty() {
+                display::print_error("Daily note cannot be empty");
+                std::process::exit(1);
+            }
+
+            let id = match daily::create(conn, target_date, &trimmed, &daily_tags, category.as_deref()) {
+                Ok(id) => id,
+                Err(e) => {
+                    display::print_error(&format!("Failed to create daily note: {}", e));
+                    std::process::exit(1);
+                }
+            };
+            use colored::Colorize;
+            println!(
+                "{} Daily note created (ID: {}, {})",
+                "✓".green().bold(),
+                id.to_string().cyan(),
+                target_date
+            );
+
+            if let Some(prev) = previous {
+                match daily::carry_over(conn, &prev, id) {
+                    Ok(0) => {}
+                    Ok(count) => println!(
+                        "  Carried over {} item(s) from {}",
+                        count,
+                        prev.created_at.format("%Y-%m-%d")
+                    ),
+                    Err(e) => {
+                        display::print_error(&format!("Failed to carry over items: {}", e));
+                        std::process::exit(1);
+                    }
+                }
+            }
+        }
+        Err(e) => {
+            display::print_error(&format!("Editor error: {}", e));
+            std::process::exit(1);
+        }
+    }
+}
+
+fn cmd_periodic(
+    conn: &rusqlite::Connection,
+    period: periodic::Period,
+    show: bool,
+    date: Option<String>,
+    template_name: Option<String>,
+) {
+    use chrono::Local;
+    use colored::Colorize;
+
+    let today = Local::now().date_naive();
+    let target_date = match date.as_deref() {
+        Some(d) => match utils::parse_date_expr(d, today) {
+            Some(nd) => nd,
+            None => {
+                display::print_error(
+                    "Invalid date. Use YYYY-MM-DD, yesterday, -2d, friday or 'last friday'",
+                );
+                std::process::exit(1);
+            }
+        },
+        None => today,
+    };
+    let (start, end) = period.bounds(target_date);
+    let label = period.label(start);
+
+    let existing = match periodic::find(conn, period, target_date) {
+        Ok(n) => n,
+        Err(e) => {
+            display::print_error(&format!("Failed to load {} note: {}", period.name(), e));
+            std::process::exit(1);
+        }
+    };
+
+    if show {
+        match existing {
+            Some(n) => {
+                println!("{} Note #{} ({})\n", "---".dimmed(), n.id, label);
+                println!("{}", n.content);
+            }
+            None => {
+                display::print_error(&format!("No {} note found for {}", period.name(), label));
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
-    if show {
-        match existing {
-            Some((id, content)) => {
-                use colored::Colorize;
-                println!("{} Daily Note #{} ({})\n", "---".dimmed(), id, target_date);
-                println!("{}", content);
+    let summary = match periodic::summarize(conn, period, target_date) {
+        Ok(s) => s,
+        Err(e) => {
+            display::print_error(&format!("Failed to summarize {}: {}", label, e));
+            std::process::exit(1);
+        }
+    };
+    let block = periodic::summary_block(&summary);
+
+    if let Some(ref n) = existing {
+        let refreshed = periodic::refresh_summary(&n.content, &block);
+        match edit_with_editor(&refreshed) {
+            Ok(new_content) => {
+                let trimmed = new_content.trim().to_string();
+                if trimmed.is_empty() {
+                    display::print_error("Note content cannot be empty");
+                    std::process::exit(1);
+                }
+                match note::update(conn, n.id, &trimmed) {
+                    Ok(_) => println!("{} Note updated ({})", "✓".green().bold(), label),
+                    Err(e) => {
+                        display::print_error(&format!("Failed to update note: {}", e));
+                        std::process::exit(1);
+                    }
+                }
             }
-            None => {
-                display::print_error(&format!("No daily note found for {}", target_date));
+            Err(e) => {
+                display::print_error(&format!("Editor error: {}", e));
                 std::process::exit(1);
             }
         }
         return;
     }
 
-    // Open in editor
-    let initial = match existing {
-        Some((_, ref content)) => content.clone(),
+    let cfg = load_config();
+    let configured = match period {
+        periodic::Period::Week => cfg.weekly.template.clone(),
+        periodic::Period::Month => cfg.monthly.template.clone(),
+    };
+
+    let mut note_tags = vec![period.name().to_string()];
+    let mut category = None;
+    let initial = match template_name.or(configured) {
+        Some(name) => {
+            let tmpl = load_template(conn, &cfg.templates_dir(), &name);
+            let (front, body) = template_parts(&tmpl);
+            for tag in front.tags {
+                if !note_tags.contains(&tag) {
+                    note_tags.push(tag);
+                }
+            }
+            category = front.category;
+
+            let vars = vec![("date".to_string(), start.to_string())];
+            let mut extra = serde_json::Map::new();
+            extra.insert("summary".into(), serde_json::json!(block));
+            extra.insert(
+                "period".into(),
+                serde_json::json!({
+                    "label": label,
+                    "start": start.to_string(),
+                    "end": end.to_string(),
+                }),
+            );
+            extra.insert(
+                "stats".into(),
+                serde_json::json!({
+                    "notes_created": summary.notes_created,
+                    "todos_completed": summary.todos_completed,
+                    "top_tags": summary.top_tags,
+                }),
+            );
+            extra.insert(
+                "daily_notes".into(),
+                serde_json::to_value(&summary.daily_notes).unwrap_or_default(),
+            );
+            fill_template(conn, body, &front.vars, vars, extra)
+        }
         None => {
+            let (title, next) = match period {
+                periodic::Period::Week => ("Weekly Review", "Next week"),
+                periodic::Period::Month => ("Monthly Review", "Next month"),
+            };
             format!(
-                "{}\n## Tasks\n- [ ] \n\n## Notes\n- \n\n## Ideas\n- \n\n---\nTags: #daily\n",
-                daily_title
+                "# {} - {}\n\n{}\n\n## Highlights\n- \n\n## {}\n- \n",
+                title, label, block, next
             )
         }
     };
@@ -774,42 +2227,19 @@ fn cmd_daily(conn: &rusqlite::Connection, show: bool, date: Option<String>) {
         Ok(new_content) => {
             let trimmed = new_content.trim().to_string();
             if trimmed.is_empty() {
-                display::print_error("Daily note cannot be empty");
+                display::print_error("Note content cannot be empty");
                 std::process::exit(1);
             }
-
-            match existing {
-                Some((id, _)) => {
-                    // Update existing
-                    match note::update(conn, id, &trimmed) {
-                        Ok(_) => {
-                            use colored::Colorize;
-                            println!("{} Daily note updated ({})", "✓".green().bold(), target_date);
-                        }
-                        Err(e) => {
-                            display::print_error(&format!("Failed to update daily note: {}", e));
-                            std::process::exit(1);
-                        }
-                    }
-                }
-                None => {
-                    // Create new
-                    let daily_tags = vec!["daily".to_string()];
-                    match note::add(conn, &trimmed, &daily_tags, None, true) {
-                        Ok(id) => {
-                            use colored::Colorize;
-                            println!(
-                                "{} Daily note created (ID: {}, {})",
-                                "✓".green().bold(),
-                                id.to_string().cyan(),
-                                target_date
-                            );
-                        }
-                        Err(e) => {
-                            display::print_error(&format!("Failed to create daily note: {}", e));
-                            std::process::exit(1);
-                        }
-                    }
+            match periodic::create(conn, period, target_date, &trimmed, &note_tags, category.as_deref()) {
+                Ok(id) => println!(
+                    "{} Note created (ID: {}, {})",
+                    "✓".green().bold(),
+                    id.to_string().cyan(),
+                    label
+                ),
+                Err(e) => {
+                    display::print_error(&format!("Failed to create note: {}", e));
+                    std::process::exit(1);
                 }
             }
         }
@@ -836,6 +2266,22 @@ fn cmd_tags(
                 std::process::exit(1);
             }
         }
+        let filter = todo::TodoFilter {
+            tag: Some(&tag_name),
+            include_deferred: true,
+            ..Default::default()
+        };
+        match todo::list_todos(conn, &filter) {
+            Ok(todos) if !todos.is_empty() => {
+                println!();
+                display::print_todos_table(&todos, &format!("TODOs tagged '{}'", tag_name));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                display::print_error(&format!("Failed to list TODOs by tag: {}", e));
+                std::process::exit(1);
+            }
+        }
         return;
     }
 
@@ -845,7 +2291,7 @@ fn cmd_tags(
                 Ok(count) => {
                     use colored::Colorize;
                     println!(
-                        "{} Renamed tag '{}' -> '{}' ({} note{})",
+                        "{} Renamed tag '{}' -> '{}' ({} item{})",
                         "✓".green().bold(),
                         old,
                         new,
@@ -873,6 +2319,7 @@ fn cmd_tags(
 }
 
 fn cmd_template(conn: &rusqlite::Connection, action: TemplateAction) {
+    let templates_dir = load_config().templates_dir();
     match action {
         TemplateAction::Create {
             name,
@@ -915,18 +2362,23 @@ fn cmd_template(conn: &rusqlite::Connection, action: TemplateAction) {
             }
         }
 
-        TemplateAction::List => match template::list_all(conn) {
-            Ok(templates) => {
-                if templates.is_empty() {
+        TemplateAction::List => match template::list_all(conn, &templates_dir) {
+            Ok(scan) => {
+                print_skipped_files(&scan.skipped);
+                if scan.templates.is_empty() {
                     use colored::Colorize;
                     println!("{}", "No templates found.".dimmed());
                     return;
                 }
                 use colored::Colorize;
                 println!("{}\n", "Templates:".bold());
-                for t in &templates {
-                    let preview = t.content.lines().next().unwrap_or("(empty)");
-                    println!("  {} - {}", t.name.cyan(), preview.dimmed());
+                for t in &scan.templates {
+                    let preview = t.body().lines().next().unwrap_or("(empty)");
+                    let source = match t.source {
+                        template::TemplateSource::Database => String::new(),
+                        template::TemplateSource::File(_) => " [file]".yellow().to_string(),
+                    };
+                    println!("  {}{} - {}", t.name.cyan(), source, preview.dimmed());
                 }
             }
             Err(e) => {
@@ -936,7 +2388,7 @@ fn cmd_template(conn: &rusqlite::Connection, action: TemplateAction) {
         },
 
         TemplateAction::Edit { name } => {
-            let existing = match template::get(conn, &name) {
+            let existing = match template::get(conn, &templates_dir, &name) {
                 Ok(Some(t)) => t,
                 Ok(None) => {
                     display::print_error(&format!("Template '{}' not found", name));
@@ -954,7 +2406,7 @@ fn cmd_template(conn: &rusqlite::Connection, action: TemplateAction) {
                         display::print_error("Template content cannot be empty");
                         std::process::exit(1);
                     }
-                    match template::create(conn, &name, new_content.trim()) {
+                    match template::update(conn, &existing, new_content.trim()) {
                         Ok(_) => {
                             use colored::Colorize;
                             println!(
@@ -984,9 +2436,25 @@ fn cmd_template(conn: &rusqlite::Connection, action: TemplateAction) {
                     "✓".green().bold(),
                     name.cyan()
                 );
+                let file = templates_dir.join(format!("{}.md", name));
+                if file.exists() {
+                    println!(
+                        "  {}",
+                        format!("{} still provides this template", file.display()).dimmed()
+                    );
+                }
             }
             Ok(false) => {
-                display::print_error(&format!("Template '{}' not found", name));
+                let file = templates_dir.join(format!("{}.md", name));
+                if file.exists() {
+                    display::print_error(&format!(
+                        "Template '{}' is a file; remove {} instead",
+                        name,
+                        file.display()
+                    ));
+                } else {
+                    display::print_error(&format!("Template '{}' not found", name));
+                }
                 std::process::exit(1);
             }
             Err(e) => {
@@ -994,29 +2462,67 @@ fn cmd_template(conn: &rusqlite::Connection, action: TemplateAction) {
                 std::process::exit(1);
             }
         },
+
+        TemplateAction::Export { dir } => match template::export_dir(conn, &templates_dir, &dir) {
+            Ok(scan) => {
+                use colored::Colorize;
+                println!(
+                    "{} Exported {} template(s) to {}",
+                    "✓".green().bold(),
+                    scan.templates.len(),
+                    dir.display()
+                );
+                print_skipped_files(&scan.skipped);
+            }
+            Err(e) => {
+                display::print_error(&format!("Failed to export templates: {}", e));
+                std::process::exit(1);
+            }
+        },
+
+        TemplateAction::Import { dir } => match template::import_dir(conn, &dir) {
+            Ok(scan) => {
+                use colored::Colorize;
+                println!(
+                    "{} Imported {} template(s) from {}",
+                    "✓".green().bold(),
+                    scan.templates.len(),
+                    dir.display()
+                );
+                for t in &scan.templates {
+                    println!("  {}", t.name.cyan());
+                }
+                print_skipped_files(&scan.skipped);
+            }
+            Err(e) => {
+                display::print_error(&format!("Failed to import templates: {}", e));
+                std::process::exit(1);
+            }
+        },
     }
 }
 
-fn cmd_new(conn: &rusqlite::Connection, template_name: String, title: Option<String>) {
-    let tmpl = match template::get(conn, &template_name) {
-        Ok(Some(t)) => t,
-        Ok(None) => {
-            display::print_error(&format!("Template '{}' not found", template_name));
-            std::process::exit(1);
-        }
-        Err(e) => {
-            display::print_error(&format!("Failed to get template: {}", e));
-            std::process::exit(1);
-        }
-    };
-
-    let mut vars: Vec<(&str, &str)> = Vec::new();
-    let title_val = title.unwrap_or_default();
-    if !title_val.is_empty() {
-        vars.push(("title", &title_val));
+fn cmd_new(
+    conn: &rusqlite::Connection,
+    template_name: String,
+    title: Option<String>,
+    mut vars: Vec<(String, String)>,
+    tags: Option<Vec<String>>,
+    category: Option<String>,
+    todo: Option<bool>,
+) {
+    let templates_dir = load_config().templates_dir();
+    let tmpl = load_template(conn, &templates_dir, &template_name);
+    let (front, body) = template_parts(&tmpl);
+    let tags = tags.unwrap_or(front.tags);
+    let category = category.or(front.category);
+    let add_todo = todo.unwrap_or(front.todo);
+
+    if let Some(title) = title {
+        vars.push(("title".to_string(), title));
     }
 
-    let rendered = template::render(&tmpl.content, &vars);
+    let rendered = fill_template(conn, body, &front.vars, vars, serde_json::Map::new());
 
     // Open in editor for further editing
     match edit_with_editor(&rendered) {
@@ -1026,12 +2532,30 @@ fn cmd_new(conn: &rusqlite::Connection, template_name: String, title: Option<Str
                 display::print_error("Note content cannot be empty");
                 std::process::exit(1);
             }
-            match note::add(conn, trimmed, &[], None, false) {
-                Ok(id) => display::print_note_added(id, trimmed),
+            let id = match note::add(conn, trimmed, &tags, category.as_deref(), false) {
+                Ok(id) => id,
                 Err(e) => {
                     display::print_error(&format!("Failed to add note: {}", e));
                     std::process::exit(1);
                 }
+            };
+            display::print_note_added(id, trimmed);
+
+            if add_todo {
+                let heading = trimmed
+                    .lines()
+                    .next()
+                    .unwrap_or("")
+                    .trim_start_matches('#')
+                    .trim();
+                let task = format!("Follow up: {} (note #{})", heading, id);
+                match todo::add(conn, &task, "medium", None, None, &tags, None) {
+                    Ok(todo_id) => display::print_todo_added(todo_id, &task),
+                    Err(e) => {
+                        display::print_error(&format!("Failed to add TODO: {}", e));
+                        std::process::exit(1);
+                    }
+                }
             }
         }
         Err(e) => {
@@ -1041,6 +2565,7 @@ fn cmd_new(conn: &rusqlite::Connection, template_name: String, title: Option<Str
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn cmd_export(
     conn: &rusqlite::Connection,
     format: String,
@@ -1048,35 +2573,379 @@ fn cmd_export(
     tag: Option<String>,
     from: Option<String>,
     to: Option<String>,
+    journal: bool,
+    columns: Option<String>,
 ) {
-    match export::export_notes(conn, &format, tag.as_deref(), from.as_deref(), to.as_deref()) {
-        Ok(content) => {
-            if let Some(path) = output {
-                match fs::write(&path, &content) {
-                    Ok(_) => {
-                        use colored::Colorize;
-                        println!(
-                            "{} Exported to {}",
-                            "✓".green().bold(),
-                            path.cyan()
-                        );
-                    }
-                    Err(e) => {
-                        display::print_error(&format!("Failed to write file: {}", e));
-                        std::process::exit(1);
-                    }
-                }
+    if columns.is_some() && format != "csv" {
+        display::print_error("--columns only applies to --format csv");
+        std::process::exit(1);
+    }
+
+    if format == "html" {
+        let dir = match output {
+            Some(d) => PathBuf::from(d),
+            None => {
+                display::print_error("The html format needs --output <directory>");
+                std::process::exit(1);
+            }
+        };
+        match export::export_html(conn, tag.as_deref(), from.as_deref(), to.as_deref(), &dir) {
+            Ok(report) => {
+                use colored::Colorize;
+                println!(
+                    "{} Exported {} notes and {} tag pages to {}",
+                    "✓".green().bold(),
+                    report.notes,
+                    report.tags,
+                    dir.join("index.html").display().to_string().cyan()
+                );
+            }
+            Err(e) => {
+                display::print_error(&format!("Export failed: {:#}", e));
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let layout = match format.as_str() {
+        "md-dir" => Some(export::DirLayout::Flat),
+        "obsidian" => Some(export::DirLayout::Obsidian),
+        _ => None,
+    };
+    if let Some(layout) = layout {
+        let dir = match output {
+            Some(d) => PathBuf::from(d),
+            None => {
+                display::print_error(&format!("The {} format needs --output <directory>", format));
+                std::process::exit(1);
+            }
+        };
+        match export::export_to_dir(conn, tag.as_deref(), from.as_deref(), to.as_deref(), &dir, layout) {
+            Ok(report) => {
+                use colored::Colorize;
+                println!(
+                    "{} Exported to {} ({} written, {} unchanged, {} removed)",
+                    "✓".green().bold(),
+                    dir.display().to_string().cyan(),
+                    report.written,
+                    report.unchanged,
+                    report.removed
+                );
+            }
+            Err(e) => {
+                display::print_error(&format!("Export failed: {:#}", e));
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    match export::export_notes(
+        conn,
+        &format,
+        tag.as_deref(),
+        from.as_deref(),
+        to.as_deref(),
+        journal,
+        columns.as_deref(),
+    ) {
+        Ok(content) => write_output(output, &content),
+        Err(e) => {
+            display::print_error(&format!("Export failed: {:#}", e));
+            std::process::exit(1);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn cmd_import(
+    conn: &rusqlite::Connection,
+    path: String,
+    format: String,
+    replace: bool,
+    match_by: String,
+    dry_run: bool,
+    map: Vec<(String, String)>,
+    skip_attachments: bool,
+) {
+    use colored::Colorize;
+
+    let matching = match import::Match::parse(&match_by) {
+        Some(m) => m,
+        None => {
+            display::print_error(&format!("Invalid --match '{}'. Use content or id", match_by));
+            std::process::exit(1);
+        }
+    };
+
+    // TODO headlines from an Org file, imported after the notes
+    let mut todos = Vec::new();
+    // Files embedded in an Evernote export, per note, saved for the notes
+    // that were imported, and the parts of its notes that were left out
+    let mut attachments = Vec::new();
+    let mut left_out = Vec::new();
+
+    let parsed = match format.as_str() {
+        "json" => fs::read_to_string(&path)
+            .with_context(|| format!("cannot read {}", path))
+            .and_then(|content| import::parse_json(&content))
+            .map(|notes| (notes, Vec::new())),
+        "markdown" | "md" => import::parse_markdown_dir(Path::new(&path), import::MarkdownFlavor::Plain)
+            .map(|scan| (scan.notes, scan.skipped)),
+        "obsidian" => import::parse_markdown_dir(Path::new(&path), import::MarkdownFlavor::Obsidian)
+            .map(|scan| (scan.notes, scan.skipped)),
+        "csv" => fs::read_to_string(&path)
+            .with_context(|| format!("cannot read {}", path))
+            .and_then(|content| csvfile::parse_notes(&content, &map))
+            .map(|notes| (notes, Vec::new())),
+        "org" => fs::read_to_string(&path)
+            .with_context(|| format!("cannot read {}", path))
+            .map(|content| {
+                let doc = org::parse(&content);
+                todos = doc.todos;
+                (doc.notes, Vec::new())
+            }),
+        "enex" => {
+            let dir = load_config().attachments_dir();
+            fs::File::open(&path)
+                .with_context(|| format!("cannot read {}", path))
+                .and_then(|file| enex::parse(io::BufReader::new(file), (!skip_attachments).then_some(dir.as_path())))
+                .map(|scan| {
+                    attachments = scan.attachments;
+                    left_out = scan.skipped;
+                    (scan.notes, Vec::new())
+                })
+        }
+        _ => {
+            display::print_error(&format!(
+                "Unsupported import format '{}'. Use json, markdown, obsidian, csv, org or enex",
+                format
+            ));
+            std::process::exit(1);
+        }
+    };
+    let (notes, unreadable) = match parsed {
+        Ok(p) => p,
+        Err(e) => {
+            display::print_error(&format!("Failed to read {}: {:#}", path, e));
+            std::process::exit(1);
+        }
+    };
+
+    let opts = import::ImportOptions {
+        mode: if replace {
+            import::Mode::Replace
+        } else {
+            import::Mode::Merge
+        },
+        matching,
+        dry_run,
+    };
+
+    let inserted = match import::import_notes(conn, &notes, &opts) {
+        Ok(report) => {
+            let prefix = if dry_run {
+                "Dry run:".yellow().bold().to_string()
             } else {
-                println!("{}", content);
+                "✓".green().bold().to_string()
+            };
+            let verb = if dry_run { "Would import" } else { "Imported" };
+            println!(
+                "{} {} {} note{} from {} ({} skipped as duplicates)",
+                prefix,
+                verb,
+                report.imported.to_string().cyan(),
+                if report.imported == 1 { "" } else { "s" },
+                path,
+                report.skipped
+            );
+            print_skipped_files(&unreadable);
+            if replace {
+                println!(
+                    "  {} {} existing note{}",
+                    if dry_run { "Would replace" } else { "Replaced" },
+                    report.removed,
+                    if report.removed == 1 { "" } else { "s" }
+                );
             }
+            report.inserted
+        }
+        Err(e) => {
+            display::print_error(&format!("Import failed: {:#}", e));
+            std::process::exit(1);
+        }
+    };
+
+    // Only notes that were written get their files, once the import has
+    // committed
+    let imported = inserted.iter().filter_map(|&i| attachments.get(i)).flatten();
+    match enex::save_attachments(imported, dry_run) {
+        Ok(0) => {}
+        Ok(n) if dry_run => println!("  Would store {} attachment(s)", n),
+        Ok(n) => println!(
+            "  Stored {} attachment(s) in {}",
+            n.to_string().cyan(),
+            load_config().attachments_dir().display()
+        ),
+        Err(e) => {
+            display::print_error(&format!("Failed to store attachments: {:#}", e));
+            std::process::exit(1);
+        }
+    }
+    if !left_out.is_empty() {
+        println!("  Left out {} item(s):", left_out.len());
+        for (title, reason) in &left_out {
+            println!("    {} {}", title, format!("({})", reason).dimmed());
         }
+    }
+
+    if todos.is_empty() {
+        return;
+    }
+    // TODOs exported from a note's checkboxes come back with the note
+    let note_ids: Vec<i64> = notes.iter().map(|n| n.id).filter(|id| *id > 0).collect();
+    todos.retain(|t| !t.note_id.is_some_and(|id| note_ids.contains(&id)));
+
+    match todo::import_deduped(conn, &todos, dry_run) {
+        Ok(report) => println!(
+            "  {} {} TODO{} ({} skipped as duplicates)",
+            if dry_run { "Would import" } else { "Imported" },
+            report.imported.to_string().cyan(),
+            if report.imported == 1 { "" } else { "s" },
+            report.skipped
+        ),
+        Err(e) => {
+            display::print_error(&format!("TODO import failed: {}", e));
+            std::process::exit(1);
+        }
+    }
+}
+
+/// List files an import or scan passed over, with the reason for each.
+fn print_skipped_files(skipped: &[(PathBuf, String)]) {
+    use colored::Colorize;
+
+    if skipped.is_empty() {
+        return;
+    }
+    println!("  Skipped {} file(s):", skipped.len());
+    for (file, reason) in skipped {
+        println!("    {} {}", file.display(), format!("({})", reason).dimmed());
+    }
+}
+
+/// Take a labelled snapshot in the backup directory, exiting on failure.
+fn auto_backup(conn: &rusqlite::Connection, label: &str) -> PathBuf {
+    let cfg = load_config();
+    match backup::create_in(conn, &cfg.backups_dir(), Some(label), cfg.backup.keep) {
+        Ok(path) => path,
         Err(e) => {
-            display::print_error(&format!("Export failed: {}", e));
+            display::print_error(&format!("Failed to back up database: {:#}", e));
             std::process::exit(1);
         }
     }
 }
 
+fn cmd_backup(conn: &mut rusqlite::Connection, action: BackupAction) {
+    use colored::Colorize;
+
+    let cfg = load_config();
+    let dir = cfg.backups_dir();
+
+    match action {
+        BackupAction::Create { path } => {
+            let result = match path {
+                Some(p) => backup::snapshot(conn, &p).map(|_| p),
+                None => backup::create_in(conn, &dir, None, cfg.backup.keep),
+            };
+            match result {
+                Ok(p) => println!("{} Backup written to {}", "✓".green().bold(), p.display()),
+                Err(e) => {
+                    display::print_error(&format!("Backup failed: {:#}", e));
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        BackupAction::List => match backup::list(&dir) {
+            Ok(backups) if backups.is_empty() => {
+                println!("{}", format!("No backups in {}", dir.display()).dimmed());
+            }
+            Ok(backups) => {
+                println!("{}\n", format!("Backups in {}:", dir.display()).bold());
+                for b in &backups {
+                    let name = b.path.file_name().and_then(|n| n.to_str()).unwrap_or("?");
+                    println!(
+                        "  {}  {}  {}",
+                        name.cyan(),
+                        b.modified.format("%Y-%m-%d %H:%M"),
+                        format!("{} KB", b.size.div_ceil(1024)).dimmed()
+                    );
+                }
+            }
+            Err(e) => {
+                display::print_error(&format!("Failed to list backups: {:#}", e));
+                std::process::exit(1);
+            }
+        },
+
+        BackupAction::Restore { backup: name } => {
+            let src = match backup::resolve(&dir, &name) {
+                Some(p) => p,
+                None => {
+                    display::print_error(&format!("Backup '{}' not found", name));
+                    std::process::exit(1);
+                }
+            };
+
+            let safety = match backup::restore_in(conn, &dir, &src, cfg.backup.keep) {
+                Ok(p) => p,
+                Err(e) => {
+                    display::print_error(&format!("Restore failed: {:#}", e));
+                    std::process::exit(1);
+                }
+            };
+            // The snapshot may predate the current schema
+            if let Err(e) = db::initialize(conn) {
+                display::print_error(&format!("Failed to migrate restored database: {}", e));
+                std::process::exit(1);
+            }
+
+            println!("{} Restored {}", "✓".green().bold(), src.display());
+            println!(
+                "  {}",
+                format!("Previous database saved to {}", safety.display()).dimmed()
+            );
+        }
+    }
+}
+
+/// Write exported content to a file, or to stdout when no path is given.
+fn write_output(output: Option<String>, content: &str) {
+    if let Some(path) = output {
+        match fs::write(&path, content) {
+            Ok(_) => {
+                use colored::Colorize;
+                println!(
+                    "{} Exported to {}",
+                    "✓".green().bold(),
+                    path.cyan()
+                );
+            }
+            Err(e) => {
+                display::print_error(&format!("Failed to write file: {}", e));
+                std::process::exit(1);
+            }
+        }
+    } else if content.ends_with('\n') {
+        // Formats such as iCalendar end every line themselves (with CRLF)
+        print!("{}", content);
+    } else {
+        println!("{}", content);
+    }
+}
+
 fn cmd_stats(conn: &rusqlite::Connection, show_tags: bool) {
     use colored::Colorize;
 
@@ -1094,6 +2963,10 @@ fn cmd_stats(conn: &rusqlite::Connection, show_tags: bool) {
         todo_completed.to_string().green(),
         todo_pending.to_string().yellow()
     );
+    let archived = todo::count_archived(conn).unwrap_or(0);
+    if archived > 0 {
+        println!("  Archived TODOs:     {}", archived.to_string().dimmed());
+    }
     println!("  Tags:               {} unique tags", unique_tags.to_string().cyan());
 
     // Notes today
@@ -1103,11 +2976,17 @@ fn cmd_stats(conn: &rusqlite::Connection, show_tags: bool) {
     println!("\n{}:", "Activity".bold());
     println!("  Today:              {} notes", today_notes.to_string().cyan());
 
+    let projects = todo::project_progress(conn).unwrap_or_default();
+    if !projects.is_empty() {
+        println!("\n{}:", "Projects".bold());
+        display::print_project_progress(&projects);
+    }
+
     if show_tags && !tag_list.is_empty() {
         println!("\n{}:", "Top Tags".bold());
         for (i, t) in tag_list.iter().take(10).enumerate() {
             println!(
-                "  {}. {} ({} notes)",
+                "  {}. {} ({} uses)",
                 i + 1,
                 t.tag.cyan(),
                 t.count
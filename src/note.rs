@@ -1,8 +1,10 @@
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, NaiveDate};
 use rusqlite::{params, Connection, Result as SqlResult};
 use serde::Serialize;
 
-use crate::utils::timestamp_to_local;
+use crate::tags;
+use crate::todo;
+use crate::utils::{day_bounds, timestamp_to_local};
 
 #[derive(Debug, Serialize)]
 pub struct Note {
@@ -23,10 +25,22 @@ pub fn add(
     is_daily: bool,
 ) -> SqlResult<i64> {
     let now = Local::now().timestamp();
+    add_at(conn, content, tags, category, is_daily, now)
+}
 
+/// Like `add`, but with an explicit creation time, e.g. for a daily note
+/// written for a past date.
+pub fn add_at(
+    conn: &Connection,
+    content: &str,
+    tags: &[String],
+    category: Option<&str>,
+    is_daily: bool,
+    created_at: i64,
+) -> SqlResult<i64> {
     conn.execute(
         "INSERT INTO notes (content, created_at, updated_at, category, is_daily) VALUES (?1, ?2, ?3, ?4, ?5)",
-        params![content, now, now, category, is_daily],
+        params![content, created_at, created_at, category, is_daily],
     )?;
 
     let note_id = conn.last_insert_rowid();
@@ -39,11 +53,39 @@ pub fn add(
 
     // Insert tags
     for tag in tags {
-        conn.execute(
-            "INSERT INTO tags (note_id, tag) VALUES (?1, ?2)",
-            params![note_id, tag.trim()],
-        )?;
+        tags::add_tag(conn, note_id, tag.trim())?;
+    }
+
+    todo::sync_from_note(conn, note_id, content)?;
+
+    Ok(note_id)
+}
+
+/// Insert a fully specified note, e.g. one read from an import. With
+/// `keep_id` the note keeps its `id`; otherwise a new one is assigned.
+pub fn insert(conn: &Connection, note: &Note, keep_id: bool) -> SqlResult<i64> {
+    conn.execute(
+        "INSERT INTO notes (id, content, created_at, updated_at, category, is_daily) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            keep_id.then_some(note.id),
+            note.content,
+            note.created_at.timestamp(),
+            note.updated_at.timestamp(),
+            note.category,
+            note.is_daily,
+        ],
+    )?;
+
+    let note_id = conn.last_insert_rowid();
+    conn.execute(
+        "INSERT INTO notes_fts (rowid, content) VALUES (?1, ?2)",
+        params![note_id, note.content],
+    )?;
+    for tag in &note.tags {
+        tags::add_tag(conn, note_id, tag.trim())?;
     }
+    todo::sync_from_note(conn, note_id, &note.content)?;
 
     Ok(note_id)
 }
@@ -155,9 +197,33 @@ pub fn get_by_id(conn: &Connection, id: i64) -> SqlResult<Option<Note>> {
     }
 }
 
+/// Daily notes created between two dates (inclusive), oldest first.
+pub fn list_daily(conn: &Connection, from: NaiveDate, to: NaiveDate) -> SqlResult<Vec<Note>> {
+    let (start, _) = day_bounds(from);
+    let (_, end) = day_bounds(to);
+
+    let mut stmt = conn.prepare(
+        "SELECT id FROM notes WHERE is_daily = 1 AND created_at >= ?1 AND created_at <= ?2 \
+         ORDER BY created_at ASC",
+    )?;
+    let ids = stmt
+        .query_map(params![start, end], |row| row.get(0))?
+        .collect::<SqlResult<Vec<i64>>>()?;
+
+    let mut notes = Vec::new();
+    for id in ids {
+        if let Some(n) = get_by_id(conn, id)? {
+            notes.push(n);
+        }
+    }
+    Ok(notes)
+}
+
 pub fn delete(conn: &Connection, id: i64) -> SqlResult<bool> {
     conn.execute("DELETE FROM notes_fts WHERE rowid = ?1", params![id])?;
     conn.execute("DELETE FROM tags WHERE note_id = ?1", params![id])?;
+    // Extracted TODOs outlive their note; they just lose the link
+    conn.execute("UPDATE todos SET note_id = NULL WHERE note_id = ?1", params![id])?;
     let affected = conn.execute("DELETE FROM notes WHERE id = ?1", params![id])?;
     Ok(affected > 0)
 }
@@ -175,6 +241,8 @@ pub fn update(conn: &Connection, id: i64, content: &str) -> SqlResult<bool> {
             "INSERT INTO notes_fts (rowid, content) VALUES (?1, ?2)",
             params![id, content],
         )?;
+
+        todo::sync_from_note(conn, id, content)?;
     }
     Ok(affected > 0)
 }
@@ -0,0 +1,613 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveTime};
+use rusqlite::{Connection, Result as SqlResult};
+use std::collections::HashMap;
+
+use crate::note::Note;
+use crate::todo::{self, is_closed_status, Todo, TodoFilter, STATUSES};
+use crate::utils::{day_bounds, note_title, timestamp_to_local};
+
+/// Render notes as top-level Org headings, followed by TODOs as `TODO`/`DONE`
+/// headlines. Metadata goes in `PROPERTIES` drawers and planning lines;
+/// note bodies are converted from Markdown so they read naturally in Org.
+pub fn export(conn: &Connection, tag: Option<&str>, notes: &[Note]) -> SqlResult<String> {
+    let filter = TodoFilter {
+        tag,
+        include_deferred: true,
+        archived: None,
+        ..Default::default()
+    };
+    let mut todos = todo::list_todos(conn, &filter)?;
+    todos.sort_by_key(|t| t.id);
+
+    let mut out = vec![
+        "#+TITLE: notectl".to_string(),
+        format!("#+DATE: {}", timestamp(&Local::now(), false)),
+        String::new(),
+    ];
+    for n in notes {
+        push_note(&mut out, n);
+    }
+    for t in &todos {
+        push_todo(&mut out, t);
+    }
+    Ok(out.join("\n"))
+}
+
+fn push_note(out: &mut Vec<String>, n: &Note) {
+    let title = match note_title(&n.content) {
+        "" => format!("Note {}", n.id),
+        t => t.to_string(),
+    };
+    out.push(headline(&title, &n.tags));
+
+    out.push(":PROPERTIES:".to_string());
+    out.push(property("ID", &n.id.to_string()));
+    out.push(property("CREATED", &timestamp(&n.created_at, false)));
+    out.push(property("UPDATED", &timestamp(&n.updated_at, false)));
+    if let Some(ref category) = n.category {
+        out.push(property("CATEGORY", category));
+    }
+    if n.is_daily {
+        out.push(property("DAILY", "t"));
+    }
+    out.push(":END:".to_string());
+
+    // The first line became the headline
+    let body: Vec<&str> = n
+        .content
+        .lines()
+        .skip_while(|l| l.trim().is_empty())
+        .skip(1)
+        .collect();
+    out.extend(markdown_to_org(&body.join("\n")));
+    out.push(String::new());
+}
+
+fn push_todo(out: &mut Vec<String>, t: &Todo) {
+    let keyword = if t.completed { "DONE" } else { "TODO" };
+    let cookie = match t.priority.as_str() {
+        "high" => "[#A] ",
+        "low" => "[#C] ",
+        _ => "",
+    };
+    out.push(headline(&format!("{} {}{}", keyword, cookie, t.task), &t.tags));
+
+    let mut planning = Vec::new();
+    if let Some(done) = t.completed_at {
+        planning.push(format!("CLOSED: {}", timestamp(&done, false)));
+    }
+    if let Some(due) = t.due_date {
+        planning.push(format!("DEADLINE: {}", date_stamp(due.date_naive())));
+    }
+    if let Some(start) = t.defer_until {
+        planning.push(format!("SCHEDULED: {}", date_stamp(start.date_naive())));
+    }
+    if !planning.is_empty() {
+        out.push(planning.join(" "));
+    }
+
+    out.push(":PROPERTIES:".to_string());
+    out.push(property("ID", &t.id.to_string()));
+    out.push(property("CREATED", &timestamp(&t.created_at, false)));
+    if let Some(ref project) = t.project {
+        out.push(property("PROJECT", project));
+    }
+    let default_status = if t.completed { "done" } else { "todo" };
+    if t.status != default_status {
+        out.push(property("STATUS", &t.status));
+    }
+    if let Some(note_id) = t.note_id {
+        out.push(property("NOTE", &note_id.to_string()));
+    }
+    out.push(":END:".to_string());
+    out.push(String::new());
+}
+
+fn headline(title: &str, tags: &[String]) -> String {
+    let tags: Vec<String> = tags
+        .iter()
+        .map(|t| {
+            t.chars()
+                .map(|c| if c.is_alphanumeric() || matches!(c, '_' | '@' | '#' | '%') { c } else { '_' })
+                .collect()
+        })
+        .collect();
+    if tags.is_empty() {
+        format!("* {}", title)
+    } else {
+        format!("* {} :{}:", title, tags.join(":"))
+    }
+}
+
+fn property(name: &str, value: &str) -> String {
+    format!("{:<11} {}", format!(":{}:", name), value)
+}
+
+/// `[2026-02-14 Sat 09:30]`, or `<...>` when `active`.
+fn timestamp(dt: &DateTime<Local>, active: bool) -> String {
+    let inner = dt.format("%Y-%m-%d %a %H:%M");
+    if active {
+        format!("<{}>", inner)
+    } else {
+        format!("[{}]", inner)
+    }
+}
+
+fn date_stamp(date: NaiveDate) -> String {
+    format!("<{}>", date.format("%Y-%m-%d %a"))
+}
+
+/// Convert the Markdown constructs that mean something else in Org:
+/// headings become sub-headings, `*` bullets become `-`, fenced code
+/// becomes a source block and `[text](url)` an Org link.
+fn markdown_to_org(body: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut in_code = false;
+
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        if let Some(lang) = trimmed.strip_prefix("```") {
+            out.push(if in_code {
+                "#+END_SRC".to_string()
+            } else {
+                format!("#+BEGIN_SRC {}", lang.trim()).trim_end().to_string()
+            });
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            // Org's escape for lines that would otherwise be read as syntax
+            if line.starts_with('*') || line.starts_with("#+") {
+                out.push(format!(",{}", line));
+            } else {
+                out.push(line.to_string());
+            }
+            continue;
+        }
+
+        let hashes = line.chars().take_while(|c| *c == '#').count();
+        if hashes > 0 && line[hashes..].starts_with(' ') {
+            out.push(format!("{}{}", "*".repeat(hashes + 1), &line[hashes..]));
+        } else if let Some(item) = trimmed.strip_prefix("* ").or_else(|| trimmed.strip_prefix("+ ")) {
+            let indent = &line[..line.len() - trimmed.len()];
+            out.push(format!("{}- {}", indent, convert_links(item, md_link_to_org)));
+        } else {
+            out.push(convert_links(line, md_link_to_org));
+        }
+    }
+    if in_code {
+        out.push("#+END_SRC".to_string());
+    }
+    out
+}
+
+fn org_to_markdown(lines: &[String]) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut in_code = false;
+
+    for line in lines {
+        let trimmed = line.trim_start();
+        let upper = trimmed.to_uppercase();
+        if !in_code && upper.starts_with("#+BEGIN_SRC") {
+            out.push(format!("```{}", trimmed[11..].trim()));
+            in_code = true;
+        } else if in_code && upper.starts_with("#+END_SRC") {
+            out.push("```".to_string());
+            in_code = false;
+        } else if in_code {
+            out.push(line.strip_prefix(',').unwrap_or(line).to_string());
+        } else {
+            out.push(convert_links(line, org_link_to_md));
+        }
+    }
+    out
+}
+
+/// Rewrite every link in `line` that `convert` recognizes at a `[`.
+fn convert_links(line: &str, convert: fn(&str) -> Option<(String, usize)>) -> String {
+    let mut out = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find('[') {
+        out.push_str(&rest[..start]);
+        match convert(&rest[start..]) {
+            Some((link, len)) => {
+                out.push_str(&link);
+                rest = &rest[start + len..];
+            }
+            None => {
+                out.push('[');
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// `[text](url)` at the start of `s` as `[[url][text]]`, with the length
+/// consumed.
+fn md_link_to_org(s: &str) -> Option<(String, usize)> {
+    let text_end = s.find("](")?;
+    let text = &s[1..text_end];
+    if text.contains('[') || text.contains(']') {
+        return None;
+    }
+    let url_len = s[text_end + 2..].find(')')?;
+    let url = &s[text_end + 2..text_end + 2 + url_len];
+    if url.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((format!("[[{}][{}]]", url, text), text_end + 2 + url_len + 1))
+}
+
+/// `[[url][text]]` at the start of `s` as `[text](url)`. Bare `[[target]]`
+/// links are left alone; they read the same in Markdown wikis.
+fn org_link_to_md(s: &str) -> Option<(String, usize)> {
+    let inner = s.strip_prefix("[[")?;
+    let end = inner.find("]]")?;
+    let (url, text) = inner[..end].split_once("][")?;
+    Some((format!("[{}]({})", text, url), end + 4))
+}
+
+/// Notes and TODOs read from an Org file.
+pub struct OrgDocument {
+    pub notes: Vec<Note>,
+    /// TODO headlines. Those exported from a note's checkboxes carry the
+    /// note's id in `note_id`, so the caller can skip them when that note is
+    /// imported too and will recreate them.
+    pub todos: Vec<Todo>,
+}
+
+struct Headline {
+    level: usize,
+    keyword: Option<String>,
+    priority: Option<char>,
+    title: String,
+    /// The headline as written, TODO keyword and priority included
+    text: String,
+    tags: Vec<String>,
+    planning: HashMap<String, String>,
+    properties: HashMap<String, String>,
+    body: Vec<String>,
+}
+
+const TODO_KEYWORDS: [&str; 8] = [
+    "TODO", "NEXT", "DOING", "STARTED", "WAITING", "HOLD", "DONE", "CANCELLED",
+];
+
+/// Parse an Org file. A headline with a TODO keyword becomes a TODO; any
+/// other headline starts a note, and deeper headlines under it become
+/// Markdown headings in its body. Text before the first headline is ignored.
+pub fn parse(content: &str) -> OrgDocument {
+    let mut headlines: Vec<Headline> = Vec::new();
+    let mut drawer: Option<String> = None;
+
+    let lines: Vec<&str> = content.lines().collect();
+    for (i, line) in lines.iter().copied().enumerate() {
+        if let Some(h) = parse_headline(line) {
+            headlines.push(h);
+            drawer = None;
+            continue;
+        }
+        let current = match headlines.last_mut() {
+            Some(h) => h,
+            None => continue,
+        };
+        let trimmed = line.trim();
+
+        if let Some(name) = drawer.as_deref() {
+            if trimmed.eq_ignore_ascii_case(":END:") {
+                drawer = None;
+            } else if name == "PROPERTIES" {
+                if let Some((key, value)) = parse_property(trimmed) {
+                    current.properties.insert(key, value);
+                }
+            }
+            continue;
+        }
+        // Drawers such as :PROPERTIES: and :LOGBOOK: run to :END:; without
+        // one the line is ordinary text
+        if let Some(name) = trimmed
+            .strip_prefix(':')
+            .and_then(|s| s.strip_suffix(':'))
+            .filter(|s| {
+                !s.is_empty()
+                    && s.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_' || c == '-')
+            })
+            .filter(|_| closes_drawer(&lines[i + 1..]))
+        {
+            drawer = Some(name.to_string());
+            continue;
+        }
+        if current.body.is_empty()
+            && current.properties.is_empty()
+            && parse_planning(trimmed, &mut current.planning)
+        {
+            continue;
+        }
+        current.body.push(line.to_string());
+    }
+
+    let mut doc = OrgDocument {
+        notes: Vec::new(),
+        todos: Vec::new(),
+    };
+    // Level of the open note, the note itself, and whether notectl wrote it
+    let mut open: Option<(usize, Note, bool)> = None;
+
+    for mut h in headlines {
+        // notectl writes UPDATED for notes and never for TODOs, and never
+        // nests TODOs under a note, so a keyword there is part of the text
+        let exported = h.properties.contains_key("UPDATED");
+        let in_exported = open
+            .as_ref()
+            .is_some_and(|(level, _, exported)| *exported && h.level > *level);
+        if h.keyword.is_some() && (exported || in_exported) {
+            h.keyword = None;
+            h.priority = None;
+            h.title = std::mem::take(&mut h.text);
+        }
+
+        if h.keyword.is_some() {
+            if open.as_ref().is_some_and(|(level, _, _)| h.level <= *level) {
+                doc.notes.extend(open.take().map(|(_, n, _)| n));
+            }
+            if let Some(t) = headline_todo(&h) {
+                doc.todos.push(t);
+            }
+            continue;
+        }
+
+        match open {
+            Some((level, ref mut note, _)) if h.level > level => {
+                if !note.content.contains('\n') {
+                    note.content = format!("# {}", note.content);
+                }
+                note.content.push_str(&format!(
+                    "\n{} {}",
+                    "#".repeat(h.level - level),
+                    h.title
+                ));
+                for line in org_to_markdown(&h.body) {
+                    note.content.push('\n');
+                    note.content.push_str(&line);
+                }
+                for tag in h.tags {
+                    if !note.tags.contains(&tag) {
+                        note.tags.push(tag);
+                    }
+                }
+            }
+            _ => {
+                doc.notes.extend(open.take().map(|(_, n, _)| n));
+                open = Some((h.level, headline_note(h), exported));
+            }
+        }
+    }
+    doc.notes.extend(open.map(|(_, n, _)| n));
+
+    for n in &mut doc.notes {
+        n.content = n.content.trim_end().to_string();
+    }
+    doc
+}
+
+fn parse_headline(line: &str) -> Option<Headline> {
+    let level = line.chars().take_while(|c| *c == '*').count();
+    if level == 0 {
+        return None;
+    }
+    let mut rest = line[level..].strip_prefix(' ')?.trim();
+
+    let mut tags = Vec::new();
+    if let Some((head, last)) = rest.rsplit_once(char::is_whitespace) {
+        if last.len() > 2 && last.starts_with(':') && last.ends_with(':') {
+            tags = last
+                .trim_matches(':')
+                .split(':')
+                .filter(|t| !t.is_empty())
+                .map(|t| t.to_string())
+                .collect();
+            rest = head.trim_end();
+        }
+    }
+
+    let text = rest.to_string();
+    let mut keyword = None;
+    let (first, after) = rest.split_once(' ').unwrap_or((rest, ""));
+    if TODO_KEYWORDS.contains(&first) || first == "CANCELED" {
+        keyword = Some(first.to_string());
+        rest = after.trim_start();
+    }
+
+    let mut priority = None;
+    if let Some(after) = rest.strip_prefix("[#") {
+        let mut chars = after.chars();
+        // Priority cookies are a single letter, so the slice below stays on
+        // a character boundary
+        if let (Some(p), Some(']')) = (chars.next(), chars.next()) {
+            if p.is_ascii_alphabetic() {
+                priority = Some(p.to_ascii_uppercase());
+                rest = after[2..].trim_start();
+            }
+        }
+    }
+
+    Some(Headline {
+        level,
+        keyword,
+        priority,
+        title: rest.to_string(),
+        text,
+        tags,
+        planning: HashMap::new(),
+        properties: HashMap::new(),
+        body: Vec::new(),
+    })
+}
+
+/// Whether an `:END:` line comes before the next headline.
+fn closes_drawer(lines: &[&str]) -> bool {
+    lines
+        .iter()
+        .take_while(|l| parse_headline(l).is_none())
+        .any(|l| l.trim().eq_ignore_ascii_case(":END:"))
+}
+
+fn parse_property(line: &str) -> Option<(String, String)> {
+    let rest = line.strip_prefix(':')?;
+    let (key, value) = rest.split_once(':')?;
+    Some((key.trim().to_uppercase(), value.trim().to_string()))
+}
+
+/// Read a `DEADLINE: <...> SCHEDULED: <...> CLOSED: [...]` line into
+/// `planning`. Returns false if `line` isn't one.
+fn parse_planning(line: &str, planning: &mut HashMap<String, String>) -> bool {
+    const KEYWORDS: [&str; 3] = ["DEADLINE:", "SCHEDULED:", "CLOSED:"];
+    if !KEYWORDS.iter().any(|k| line.starts_with(k)) {
+        return false;
+    }
+    for keyword in KEYWORDS {
+        let Some(pos) = line.find(keyword) else {
+            continue;
+        };
+        let after = line[pos + keyword.len()..].trim_start();
+        let close = match after.chars().next() {
+            Some('<') => '>',
+            Some('[') => ']',
+            _ => continue,
+        };
+        if let Some(end) = after.find(close) {
+            planning.insert(keyword.trim_end_matches(':').to_string(), after[..=end].to_string());
+        }
+    }
+    true
+}
+
+/// The date and optional time in an Org timestamp like `<2026-02-14 Sat>`
+/// or `[2026-02-14 Sat 09:30]`.
+fn parse_timestamp(s: &str) -> Option<(NaiveDate, Option<NaiveTime>)> {
+    let inner = s.trim().trim_start_matches(['<', '[']).trim_end_matches(['>', ']']);
+    let mut parts = inner.split_whitespace();
+    let date = NaiveDate::parse_from_str(parts.next()?, "%Y-%m-%d").ok()?;
+    let time = parts.find_map(|p| NaiveTime::parse_from_str(p.get(..5).unwrap_or(p), "%H:%M").ok());
+    Some((date, time))
+}
+
+fn to_local(date: NaiveDate, time: Option<NaiveTime>) -> DateTime<Local> {
+    match time {
+        Some(t) => date
+            .and_time(t)
+            .and_local_timezone(Local)
+            .earliest()
+            .unwrap_or_else(|| timestamp_to_local(day_bounds(date).0)),
+        None => timestamp_to_local(day_bounds(date).0),
+    }
+}
+
+fn property_time(h: &Headline, key: &str) -> Option<DateTime<Local>> {
+    h.properties
+        .get(key)
+        .and_then(|v| parse_timestamp(v))
+        .map(|(d, t)| to_local(d, t))
+}
+
+fn headline_note(h: Headline) -> Note {
+    let now = Local::now();
+    let created_at = property_time(&h, "CREATED").unwrap_or(now);
+    let updated_at = property_time(&h, "UPDATED").unwrap_or(created_at);
+
+    let body = org_to_markdown(&h.body).join("\n");
+    let body = body.trim_end();
+    // A one-line note stays one line; otherwise the headline is its title
+    let content = if body.trim().is_empty() {
+        h.title.clone()
+    } else {
+        format!("# {}\n{}", h.title, body)
+    };
+
+    Note {
+        id: h
+            .properties
+            .get("ID")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0),
+        content,
+        created_at,
+        updated_at,
+        category: h.properties.get("CATEGORY").cloned(),
+        is_daily: h.properties.get("DAILY").is_some_and(|v| v == "t"),
+        tags: h.tags,
+    }
+}
+
+fn headline_todo(h: &Headline) -> Option<Todo> {
+    if h.title.is_empty() {
+        return None;
+    }
+
+    let keyword_status = match h.keyword.as_deref() {
+        Some("NEXT" | "DOING" | "STARTED") => "doing",
+        Some("WAITING" | "HOLD") => "waiting",
+        Some("DONE") => "done",
+        Some("CANCELLED" | "CANCELED") => "cancelled",
+        _ => "todo",
+    };
+    let status = h
+        .properties
+        .get("STATUS")
+        .map(|s| s.to_lowercase())
+        .filter(|s| STATUSES.contains(&s.as_str()) && is_closed_status(s) == is_closed_status(keyword_status))
+        .unwrap_or_else(|| keyword_status.to_string());
+    let completed = is_closed_status(&status);
+
+    let priority = match h.priority {
+        Some('A') => "high",
+        Some('B') | None => "medium",
+        Some(_) => "low",
+    };
+
+    let planned = |key: &str| h.planning.get(key).and_then(|v| parse_timestamp(v));
+    let closed = planned("CLOSED").map(|(d, t)| to_local(d, t));
+
+    Some(Todo {
+        id: 0,
+        task: h.title.clone(),
+        completed,
+        status,
+        priority: priority.to_string(),
+        due_date: planned("DEADLINE").map(|(d, _)| timestamp_to_local(day_bounds(d).1)),
+        created_at: property_time(h, "CREATED").unwrap_or_else(Local::now),
+        project: h.properties.get("PROJECT").cloned(),
+        tags: h.tags.clone(),
+        note_id: h.properties.get("NOTE").and_then(|v| v.parse().ok()),
+        blocked_by: Vec::new(),
+        tracked_secs: 0,
+        defer_until: planned("SCHEDULED").map(|(d, _)| timestamp_to_local(day_bounds(d).0)),
+        completed_at: if completed {
+            Some(closed.unwrap_or_else(Local::now))
+        } else {
+            None
+        },
+        archived: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_heading_is_imported_once() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::initialize(&conn).unwrap();
+        let doc = parse("* TODO Renew passport\n* TODO Renew passport\n* TODO Book flights\n");
+        assert_eq!(doc.todos.len(), 3);
+
+        let report = todo::import_deduped(&conn, &doc.todos, false).unwrap();
+        assert_eq!((report.imported, report.skipped), (2, 1));
+
+        let again = todo::import_deduped(&conn, &doc.todos, false).unwrap();
+        assert_eq!((again.imported, again.skipped), (0, 3));
+    }
+}
@@ -0,0 +1,205 @@
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqlResult};
+use serde::Serialize;
+
+use crate::note::{self, Note};
+use crate::tags::TagCount;
+use crate::utils::day_bounds;
+
+/// Lines delimiting the generated summary. Everything between them is
+/// rewritten each time the note is reopened; the rest is left alone.
+const SUMMARY_START: &str = "<!-- notectl:summary -->";
+const SUMMARY_END: &str = "<!-- /notectl:summary -->";
+
+#[derive(Debug, Clone, Copy)]
+pub enum Period {
+    Week,
+    Month,
+}
+
+impl Period {
+    /// Stored in `notes.period` and used as the note's tag.
+    pub fn name(self) -> &'static str {
+        match self {
+            Period::Week => "weekly",
+            Period::Month => "monthly",
+        }
+    }
+
+    /// First and last day of the period containing `date`. Weeks run
+    /// Monday to Sunday.
+    pub fn bounds(self, date: NaiveDate) -> (NaiveDate, NaiveDate) {
+        match self {
+            Period::Week => {
+                let start = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+                (start, start + Duration::days(6))
+            }
+            Period::Month => {
+                let start = date.with_day(1).unwrap_or(date);
+                let next = if start.month() == 12 {
+                    NaiveDate::from_ymd_opt(start.year() + 1, 1, 1)
+                } else {
+                    NaiveDate::from_ymd_opt(start.year(), start.month() + 1, 1)
+                };
+                (start, next.map_or(start, |n| n - Duration::days(1)))
+            }
+        }
+    }
+
+    /// `2026-W42` or `2026-10`.
+    pub fn label(self, date: NaiveDate) -> String {
+        match self {
+            Period::Week => date.format("%G-W%V").to_string(),
+            Period::Month => date.format("%Y-%m").to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub notes_created: i64,
+    pub todos_completed: i64,
+    pub top_tags: Vec<TagCount>,
+    pub daily_notes: Vec<Note>,
+}
+
+/// The periodic note covering `date`, if one exists.
+pub fn find(conn: &Connection, period: Period, date: NaiveDate) -> SqlResult<Option<Note>> {
+    let (start, end) = period.bounds(date);
+    let id: Option<i64> = conn
+        .query_row(
+            "SELECT id FROM notes WHERE period = ?1 AND created_at >= ?2 AND created_at <= ?3 \
+             ORDER BY created_at LIMIT 1",
+            params![period.name(), day_bounds(start).0, day_bounds(end).1],
+            |row| row.get(0),
+        )
+        .optional()?;
+    match id {
+        Some(id) => note::get_by_id(conn, id),
+        None => Ok(None),
+    }
+}
+
+/// Create the periodic note for the period containing `date`, stamped with
+/// the current time if the period is under way and with its first day
+/// otherwise.
+pub fn create(
+    conn: &Connection,
+    period: Period,
+    date: NaiveDate,
+    content: &str,
+    tags: &[String],
+    category: Option<&str>,
+) -> SqlResult<i64> {
+    let (start, end) = period.bounds(date);
+    let today = Local::now().date_naive();
+    let created_at = if (start..=end).contains(&today) {
+        Local::now().timestamp()
+    } else {
+        day_bounds(start).0
+    };
+
+    let id = note::add_at(conn, content, tags, category, false, created_at)?;
+    conn.execute(
+        "UPDATE notes SET period = ?1 WHERE id = ?2",
+        params![period.name(), id],
+    )?;
+    Ok(id)
+}
+
+pub fn summarize(conn: &Connection, period: Period, date: NaiveDate) -> SqlResult<Summary> {
+    let (start, end) = period.bounds(date);
+    let (from, _) = day_bounds(start);
+    let (_, to) = day_bounds(end);
+
+    let notes_created: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM notes WHERE period IS NULL AND created_at >= ?1 AND created_at <= ?2",
+        params![from, to],
+        |row| row.get(0),
+    )?;
+    let todos_completed: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM todos WHERE completed = 1 AND completed_at >= ?1 AND completed_at <= ?2",
+        params![from, to],
+        |row| row.get(0),
+    )?;
+
+    let mut stmt = conn.prepare(
+        "SELECT t.tag, COUNT(*) AS cnt FROM tags t JOIN notes n ON n.id = t.note_id \
+         WHERE n.period IS NULL AND n.created_at >= ?1 AND n.created_at <= ?2 \
+         GROUP BY t.tag ORDER BY cnt DESC, t.tag LIMIT 5",
+    )?;
+    let top_tags = stmt
+        .query_map(params![from, to], |row| {
+            Ok(TagCount {
+                tag: row.get(0)?,
+                count: row.get(1)?,
+            })
+        })?
+        .collect::<SqlResult<Vec<_>>>()?;
+
+    Ok(Summary {
+        notes_created,
+        todos_completed,
+        top_tags,
+        daily_notes: note::list_daily(conn, start, end)?,
+    })
+}
+
+/// The generated summary, including the marker lines that let
+/// `refresh_summary` find it again.
+pub fn summary_block(summary: &Summary) -> String {
+    let mut lines = vec![
+        SUMMARY_START.to_string(),
+        "## Summary".to_string(),
+        format!("- Notes created: {}", summary.notes_created),
+        format!("- TODOs completed: {}", summary.todos_completed),
+    ];
+
+    if !summary.top_tags.is_empty() {
+        let tags: Vec<String> = summary
+            .top_tags
+            .iter()
+            .map(|t| format!("#{} ({})", t.tag, t.count))
+            .collect();
+        lines.push(format!("- Top tags: {}", tags.join(", ")));
+    }
+
+    if !summary.daily_notes.is_empty() {
+        lines.push(String::new());
+        lines.push("### Daily notes".to_string());
+        for n in &summary.daily_notes {
+            let title = n
+                .content
+                .lines()
+                .next()
+                .unwrap_or("")
+                .trim_start_matches('#')
+                .trim();
+            lines.push(format!(
+                "- {} — {} (#{})",
+                n.created_at.format("%Y-%m-%d"),
+                title,
+                n.id
+            ));
+        }
+    }
+
+    lines.push(SUMMARY_END.to_string());
+    lines.join("\n")
+}
+
+/// Replace the summary block in `content` with `block`, or return the
+/// content unchanged if it has none.
+pub fn refresh_summary(content: &str, block: &str) -> String {
+    let start = match content.find(SUMMARY_START) {
+        Some(s) => s,
+        None => return content.to_string(),
+    };
+    match content[start..].find(SUMMARY_END) {
+        Some(len) => {
+            let end = start + len + SUMMARY_END.len();
+            format!("{}{}{}", &content[..start], block, &content[end..])
+        }
+        None => content.to_string(),
+    }
+}
@@ -24,15 +24,13 @@ pub fn search_notes(
         .collect::<Vec<_>>()
         .join(" AND ");
 
-    let query = format!(
-        "SELECT n.id, n.content, n.created_at, n.updated_at, n.category, n.is_daily \
+    let query = "SELECT n.id, n.content, n.created_at, n.updated_at, n.category, n.is_daily \
          FROM notes n \
          JOIN notes_fts ON notes_fts.rowid = n.id \
          WHERE notes_fts MATCH ?1 \
-         ORDER BY n.created_at DESC"
-    );
+         ORDER BY n.created_at DESC";
 
-    let mut stmt = conn.prepare(&query)?;
+    let mut stmt = conn.prepare(query)?;
     let rows = stmt.query_map(params![fts_query], |row| {
         Ok((
             row.get::<_, i64>(0)?,
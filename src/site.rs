@@ -0,0 +1,385 @@
+use anyhow::Result;
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::note::Note;
+use crate::utils::{note_title, slugify};
+
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+const STYLE: &str = "\
+body { font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif;
+       max-width: 48rem; margin: 2rem auto; padding: 0 1rem; line-height: 1.6; color: #222; }
+a { color: #2458a6; text-decoration: none; }
+a:hover { text-decoration: underline; }
+header { display: flex; justify-content: space-between; align-items: baseline; gap: 1rem;
+         border-bottom: 1px solid #ddd; margin-bottom: 1.5rem; }
+header h1 { font-size: 1.4rem; margin: 0.5rem 0; }
+#search { width: 100%; padding: 0.5rem; font-size: 1rem; margin-bottom: 1rem; box-sizing: border-box; }
+ul.notes { list-style: none; padding: 0; }
+ul.notes li { padding: 0.4rem 0; border-bottom: 1px solid #f0f0f0; }
+.meta, .date { color: #777; font-size: 0.9rem; }
+.date { display: inline-block; min-width: 9.5rem; font-variant-numeric: tabular-nums; }
+.tag { display: inline-block; background: #eef3fb; border-radius: 3px; padding: 0 0.4rem;
+       margin-right: 0.25rem; font-size: 0.85rem; }
+.tags { margin-bottom: 1.5rem; }
+pre { background: #f6f8fa; padding: 0.8rem; overflow-x: auto; }
+code { background: #f6f8fa; padding: 0 0.2rem; }
+table { border-collapse: collapse; }
+td, th { border: 1px solid #ddd; padding: 0.3rem 0.6rem; }
+";
+
+const SEARCH_SCRIPT: &str = "\
+(function () {
+  var input = document.getElementById('search');
+  var results = document.getElementById('results');
+  var notes = document.getElementById('notes');
+  input.addEventListener('input', function () {
+    var terms = input.value.toLowerCase().split(/\\s+/).filter(Boolean);
+    results.innerHTML = '';
+    if (!terms.length) {
+      results.hidden = true;
+      notes.hidden = false;
+      return;
+    }
+    window.NOTECTL_SEARCH.filter(function (n) {
+      var text = (n.title + ' ' + n.tags.join(' ') + ' ' + n.text).toLowerCase();
+      return terms.every(function (t) { return text.indexOf(t) !== -1; });
+    }).forEach(function (n) {
+      var li = document.createElement('li');
+      var date = document.createElement('span');
+      date.className = 'date';
+      date.textContent = n.date;
+      var a = document.createElement('a');
+      a.href = n.url;
+      a.textContent = n.title;
+      li.appendChild(date);
+      li.appendChild(a);
+      results.appendChild(li);
+    });
+    results.hidden = false;
+    notes.hidden = true;
+  });
+})();
+";
+
+pub struct SiteReport {
+    pub notes: usize,
+    pub tags: usize,
+}
+
+#[derive(Serialize)]
+struct SearchEntry<'a> {
+    url: String,
+    title: &'a str,
+    date: String,
+    tags: &'a [String],
+    text: &'a str,
+}
+
+/// Write a static site for `notes` into `dir`: an index with client-side
+/// search, one page per tag and one per note. Pages link to each other with
+/// relative URLs, so the folder can be opened from disk or served as-is.
+/// Note and tag pages left over from an earlier export are removed; other
+/// files in those folders are left alone.
+pub fn write(notes: &[Note], dir: &Path) -> Result<SiteReport> {
+    let mut notes: Vec<&Note> = notes.iter().collect();
+    notes.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.id.cmp(&a.id)));
+
+    let titles: Vec<String> = notes
+        .iter()
+        .map(|n| match note_title(&n.content) {
+            "" => format!("Note #{}", n.id),
+            t => t.to_string(),
+        })
+        .collect();
+    let by_title: HashMap<String, i64> = notes
+        .iter()
+        .zip(&titles)
+        .map(|(n, t)| (t.to_lowercase(), n.id))
+        .collect();
+
+    let mut tagged: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for (i, n) in notes.iter().enumerate() {
+        for tag in &n.tags {
+            tagged.entry(tag.as_str()).or_default().push(i);
+        }
+    }
+    let tag_slugs = tag_slugs(tagged.keys().copied());
+
+    fs::create_dir_all(dir.join("notes"))?;
+    fs::create_dir_all(dir.join("tags"))?;
+    fs::write(dir.join("style.css"), STYLE)?;
+    fs::write(dir.join("search.js"), SEARCH_SCRIPT)?;
+
+    let mut written = HashSet::new();
+
+    for (n, title) in notes.iter().zip(&titles) {
+        let mut body = format!(
+            "<p class=\"meta\">{}",
+            n.created_at.format(DATE_FORMAT)
+        );
+        if let Some(ref category) = n.category {
+            body.push_str(&format!(" · {}", escape(category)));
+        }
+        body.push_str("</p>\n");
+        body.push_str(&tag_links(&n.tags, &tag_slugs, "../"));
+        body.push_str(&render_markdown(&link_wiki_links(&n.content, &by_title)));
+
+        let name = format!("notes/{}.html", n.id);
+        fs::write(dir.join(&name), page(title, "../", &body))?;
+        written.insert(name);
+    }
+
+    for (tag, members) in &tagged {
+        let list: Vec<(&Note, &str)> = members.iter().map(|&i| (notes[i], titles[i].as_str())).collect();
+        let body = format!(
+            "<h2>#{}</h2>\n{}",
+            escape(tag),
+            note_list(&list, "../", None)
+        );
+        let name = format!("tags/{}.html", tag_slugs[*tag]);
+        fs::write(dir.join(&name), page(&format!("#{}", tag), "../", &body))?;
+        written.insert(name);
+    }
+
+    let all: Vec<(&Note, &str)> = notes.iter().copied().zip(titles.iter().map(|t| t.as_str())).collect();
+    let mut index = String::from(
+        "<input id=\"search\" type=\"search\" placeholder=\"Search notes…\" autofocus>\n\
+         <ul id=\"results\" class=\"notes\" hidden></ul>\n",
+    );
+    if !tagged.is_empty() {
+        index.push_str("<div class=\"tags\">");
+        for (tag, members) in &tagged {
+            index.push_str(&format!(
+                "<a class=\"tag\" href=\"tags/{}.html\">#{} ({})</a>",
+                tag_slugs[*tag],
+                escape(tag),
+                members.len()
+            ));
+        }
+        index.push_str("</div>\n");
+    }
+    index.push_str(&note_list(&all, "", Some("notes")));
+    index.push_str(
+        "<script src=\"search-index.js\"></script>\n<script src=\"search.js\"></script>\n",
+    );
+    fs::write(dir.join("index.html"), page("Notes", "", &index))?;
+
+    // A script rather than JSON, because browsers refuse to fetch() files
+    // opened straight from disk
+    let entries: Vec<SearchEntry> = notes
+        .iter()
+        .zip(&titles)
+        .map(|(n, title)| SearchEntry {
+            url: format!("notes/{}.html", n.id),
+            title,
+            date: n.created_at.format(DATE_FORMAT).to_string(),
+            tags: &n.tags,
+            text: &n.content,
+        })
+        .collect();
+    fs::write(
+        dir.join("search-index.js"),
+        format!("window.NOTECTL_SEARCH = {};\n", serde_json::to_string(&entries)?),
+    )?;
+
+    for sub in ["notes", "tags"] {
+        for entry in fs::read_dir(dir.join(sub))? {
+            let path = entry?.path();
+            let Some(file) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            let ours = file.strip_suffix(".html").is_some_and(|stem| match sub {
+                "notes" => !stem.is_empty() && stem.chars().all(|c| c.is_ascii_digit()),
+                _ => is_slug(stem),
+            });
+            if ours && !written.contains(&format!("{}/{}", sub, file)) {
+                fs::remove_file(&path)?;
+            }
+        }
+    }
+
+    Ok(SiteReport {
+        notes: notes.len(),
+        tags: tagged.len(),
+    })
+}
+
+/// File-name slug for each tag, numbered when two tags slug the same way.
+fn tag_slugs<'a>(tags: impl Iterator<Item = &'a str>) -> HashMap<&'a str, String> {
+    let mut used = HashSet::new();
+    let mut slugs = HashMap::new();
+    for tag in tags {
+        let base = slugify(tag).unwrap_or_else(|| "tag".to_string());
+        let mut slug = base.clone();
+        let mut i = 2;
+        while !used.insert(slug.clone()) {
+            slug = format!("{}-{}", base, i);
+            i += 1;
+        }
+        slugs.insert(tag, slug);
+    }
+    slugs
+}
+
+fn page(title: &str, root: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<link rel=\"stylesheet\" href=\"{}style.css\">\n</head>\n<body>\n\
+         <header><h1><a href=\"{}index.html\">Notes</a></h1>\
+         <span class=\"meta\">Exported {}</span></header>\n<main>\n{}</main>\n</body>\n</html>\n",
+        escape(title),
+        root,
+        root,
+        chrono::Local::now().format("%Y-%m-%d"),
+        body
+    )
+}
+
+fn note_list(notes: &[(&Note, &str)], root: &str, id: Option<&str>) -> String {
+    let mut out = match id {
+        Some(id) => format!("<ul id=\"{}\" class=\"notes\">\n", id),
+        None => "<ul class=\"notes\">\n".to_string(),
+    };
+    for (n, title) in notes {
+        out.push_str(&format!(
+            "<li><span class=\"date\">{}</span><a href=\"{}notes/{}.html\">{}</a></li>\n",
+            n.created_at.format(DATE_FORMAT),
+            root,
+            n.id,
+            escape(title)
+        ));
+    }
+    out.push_str("</ul>\n");
+    out
+}
+
+fn tag_links(tags: &[String], slugs: &HashMap<&str, String>, root: &str) -> String {
+    if tags.is_empty() {
+        return String::new();
+    }
+    let links: Vec<String> = tags
+        .iter()
+        .map(|t| {
+            format!(
+                "<a class=\"tag\" href=\"{}tags/{}.html\">#{}</a>",
+                root,
+                slugs[t.as_str()],
+                escape(t)
+            )
+        })
+        .collect();
+    format!("<div class=\"tags\">{}</div>\n", links.join(""))
+}
+
+/// Whether `stem` could be a name `tag_slugs` gave out.
+fn is_slug(stem: &str) -> bool {
+    !stem.is_empty()
+        && !stem.starts_with('-')
+        && !stem.ends_with('-')
+        && stem.chars().all(|c| c == '-' || (c.is_alphanumeric() && !c.is_uppercase()))
+}
+
+/// Markdown to HTML. Raw HTML in a note is shown as text rather than
+/// passed through, and links and images may only point at http(s), mailto
+/// or relative URLs, so a published site only contains markup notectl wrote.
+fn render_markdown(content: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES;
+    let events = Parser::new_ext(content, options).map(|event| match event {
+        Event::Html(raw) | Event::InlineHtml(raw) => Event::Text(raw),
+        Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Link {
+            link_type,
+            dest_url: safe_url(dest_url),
+            title,
+            id,
+        }),
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Image {
+            link_type,
+            dest_url: safe_url(dest_url),
+            title,
+            id,
+        }),
+        other => other,
+    });
+    let mut out = String::new();
+    html::push_html(&mut out, events);
+    out
+}
+
+/// `url` if its scheme is http, https or mailto, or it has none; otherwise
+/// `#`. Browsers ignore whitespace and control characters inside
+/// a scheme, so those are dropped before looking at it.
+fn safe_url(url: CowStr) -> CowStr {
+    let compact: String = url.chars().filter(|c| !c.is_ascii_whitespace() && !c.is_control()).collect();
+    let scheme = compact
+        .find([':', '/', '?', '#'])
+        .filter(|&i| compact[i..].starts_with(':'))
+        .map(|i| compact[..i].to_ascii_lowercase());
+    match scheme.as_deref() {
+        None | Some("http" | "https" | "mailto") => url,
+        Some(_) => CowStr::Borrowed("#"),
+    }
+}
+
+/// Turn `[[Title]]`, `[[Title|label]]` and `[[Title#Heading]]` into links
+/// to the page of the note with that title, outside code blocks. Links to
+/// notes that aren't exported are left as written.
+fn link_wiki_links(content: &str, by_title: &HashMap<String, i64>) -> String {
+    let mut out = Vec::new();
+    let mut in_fence = false;
+    for line in content.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        if in_fence || !line.contains("[[") {
+            out.push(line.to_string());
+            continue;
+        }
+
+        let mut rest = line;
+        let mut linked = String::new();
+        while let Some(start) = rest.find("[[") {
+            let Some(len) = rest[start + 2..].find("]]") else {
+                break;
+            };
+            let inner = &rest[start + 2..start + 2 + len];
+            let (target, label) = inner.split_once('|').unwrap_or((inner, inner));
+            let target = target.split('#').next().unwrap_or("").trim();
+
+            linked.push_str(&rest[..start]);
+            match by_title.get(&target.to_lowercase()) {
+                Some(id) => linked.push_str(&format!("[{}]({}.html)", label.trim(), id)),
+                None => linked.push_str(&rest[start..start + len + 4]),
+            }
+            rest = &rest[start + len + 4..];
+        }
+        linked.push_str(rest);
+        out.push(linked);
+    }
+    out.join("\n")
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
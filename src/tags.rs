@@ -7,9 +7,12 @@ pub struct TagCount {
     pub count: i64,
 }
 
+/// Count tag usage across notes and TODOs.
 pub fn list_all(conn: &Connection) -> SqlResult<Vec<TagCount>> {
     let mut stmt = conn.prepare(
-        "SELECT tag, COUNT(*) as cnt FROM tags GROUP BY tag ORDER BY cnt DESC",
+        "SELECT tag, COUNT(*) as cnt FROM \
+         (SELECT tag FROM tags UNION ALL SELECT tag FROM todo_tags) \
+         GROUP BY tag ORDER BY cnt DESC",
     )?;
 
     let rows = stmt.query_map([], |row| {
@@ -22,12 +25,17 @@ pub fn list_all(conn: &Connection) -> SqlResult<Vec<TagCount>> {
     rows.collect()
 }
 
+/// Rename a tag on both notes and TODOs, returning the number of items changed.
 pub fn rename(conn: &Connection, old_name: &str, new_name: &str) -> SqlResult<usize> {
-    let affected = conn.execute(
+    let notes = conn.execute(
         "UPDATE tags SET tag = ?1 WHERE tag = ?2",
         params![new_name, old_name],
     )?;
-    Ok(affected)
+    let todos = conn.execute(
+        "UPDATE todo_tags SET tag = ?1 WHERE tag = ?2",
+        params![new_name, old_name],
+    )?;
+    Ok(notes + todos)
 }
 
 pub fn add_tag(conn: &Connection, note_id: i64, tag: &str) -> SqlResult<()> {
@@ -37,11 +45,3 @@ pub fn add_tag(conn: &Connection, note_id: i64, tag: &str) -> SqlResult<()> {
     )?;
     Ok(())
 }
-
-pub fn remove_tag(conn: &Connection, note_id: i64, tag: &str) -> SqlResult<usize> {
-    let affected = conn.execute(
-        "DELETE FROM tags WHERE note_id = ?1 AND tag = ?2",
-        params![note_id, tag],
-    )?;
-    Ok(affected)
-}
@@ -1,14 +1,105 @@
 use chrono::Local;
 use rusqlite::{params, Connection, Result as SqlResult};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::engine;
+use crate::note;
+use crate::tags;
+use crate::todo::{self, TodoFilter};
+use crate::utils::{deserialize_tags, split_front_matter};
 
 #[derive(Debug, Serialize)]
 pub struct Template {
     pub name: String,
     pub content: String,
+    pub source: TemplateSource,
+}
+
+/// Where a template lives. Files in the templates directory take precedence
+/// over database templates of the same name, so a team can keep shared
+/// templates in git without local copies silently shadowing them.
+#[derive(Debug, Serialize)]
+pub enum TemplateSource {
+    Database,
+    File(PathBuf),
+}
+
+/// Defaults a template applies to the notes created from it, declared in a
+/// YAML block at the top of the template:
+///
+/// ```text
+/// ---
+/// tags: [meeting]
+/// category: work
+/// todo: true
+/// ---
+/// # Meeting: {title}
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FrontMatter {
+    #[serde(deserialize_with = "deserialize_tags")]
+    pub tags: Vec<String>,
+    pub category: Option<String>,
+    /// Also create a follow-up TODO for the note
+    pub todo: bool,
+    /// Variables the template expects, in the order they are prompted for
+    pub vars: Vec<VarSpec>,
 }
 
-pub fn create(conn: &Connection, name: &str, content: &str) -> SqlResult<()> {
+/// A declared template variable:
+///
+/// ```text
+/// vars:
+///   - name: client
+///     description: Client name
+///     default: Acme
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VarSpec {
+    pub name: String,
+    pub description: Option<String>,
+    pub default: Option<String>,
+}
+
+impl Template {
+    /// Split the template into its front matter and body. Templates without
+    /// a leading `---` block get the default front matter.
+    pub fn parts(&self) -> Result<(FrontMatter, &str), serde_norway::Error> {
+        match split_front_matter(&self.content) {
+            Some((yaml, body)) if yaml.trim().is_empty() => Ok((FrontMatter::default(), body)),
+            Some((yaml, body)) => Ok((serde_norway::from_str(yaml)?, body)),
+            None => Ok((FrontMatter::default(), &self.content)),
+        }
+    }
+
+    /// The template body without its front matter.
+    pub fn body(&self) -> &str {
+        split_front_matter(&self.content).map_or(&self.content, |(_, body)| body)
+    }
+}
+
+/// Templates found in a directory, plus the files that could not be read
+/// and why.
+pub struct TemplateScan {
+    pub templates: Vec<Template>,
+    pub skipped: Vec<(PathBuf, String)>,
+}
+
+/// Template names double as file names in the templates directory, so they
+/// can't contain path separators or `..`.
+pub fn check_name(name: &str) -> anyhow::Result<()> {
+    if name.trim().is_empty() || name.contains(['/', '\\']) || name.contains("..") {
+        anyhow::bail!("invalid template name '{}': names can't contain '/', '\\' or '..'", name);
+    }
+    Ok(())
+}
+
+pub fn create(conn: &Connection, name: &str, content: &str) -> anyhow::Result<()> {
+    check_name(name)?;
     conn.execute(
         "INSERT OR REPLACE INTO templates (name, content) VALUES (?1, ?2)",
         params![name, content],
@@ -16,28 +107,62 @@ pub fn create(conn: &Connection, name: &str, content: &str) -> SqlResult<()> {
     Ok(())
 }
 
-pub fn get(conn: &Connection, name: &str) -> SqlResult<Option<Template>> {
+pub fn get(conn: &Connection, dir: &Path, name: &str) -> anyhow::Result<Option<Template>> {
+    check_name(name)?;
+    if let Ok(t) = load_file(&dir.join(format!("{}.md", name))) {
+        return Ok(Some(t));
+    }
+    Ok(get_from_db(conn, name)?)
+}
+
+pub fn get_from_db(conn: &Connection, name: &str) -> SqlResult<Option<Template>> {
     let mut stmt = conn.prepare("SELECT name, content FROM templates WHERE name = ?1")?;
     let mut rows = stmt.query(params![name])?;
     if let Some(row) = rows.next()? {
         Ok(Some(Template {
             name: row.get(0)?,
             content: row.get(1)?,
+            source: TemplateSource::Database,
         }))
     } else {
         Ok(None)
     }
 }
 
-pub fn list_all(conn: &Connection) -> SqlResult<Vec<Template>> {
+/// All templates from the directory and the database, sorted by name, with
+/// directory files replacing database templates of the same name.
+pub fn list_all(conn: &Connection, dir: &Path) -> SqlResult<TemplateScan> {
+    let TemplateScan {
+        mut templates,
+        skipped,
+    } = load_dir(dir);
+
     let mut stmt = conn.prepare("SELECT name, content FROM templates ORDER BY name")?;
     let rows = stmt.query_map([], |row| {
         Ok(Template {
             name: row.get(0)?,
             content: row.get(1)?,
+            source: TemplateSource::Database,
         })
     })?;
-    rows.collect()
+    for row in rows {
+        let t = row?;
+        if !templates.iter().any(|f| f.name == t.name) {
+            templates.push(t);
+        }
+    }
+
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(TemplateScan { templates, skipped })
+}
+
+/// Save new content for an existing template back to wherever it came from.
+pub fn update(conn: &Connection, tmpl: &Template, content: &str) -> anyhow::Result<()> {
+    match tmpl.source {
+        TemplateSource::Database => create(conn, &tmpl.name, content)?,
+        TemplateSource::File(ref path) => fs::write(path, format!("{}\n", content))?,
+    }
+    Ok(())
 }
 
 pub fn delete(conn: &Connection, name: &str) -> SqlResult<bool> {
@@ -45,19 +170,121 @@ pub fn delete(conn: &Connection, name: &str) -> SqlResult<bool> {
     Ok(affected > 0)
 }
 
-pub fn render(template_content: &str, vars: &[(&str, &str)]) -> String {
-    let mut result = template_content.to_string();
+/// Read every `*.md` file in `dir` as a template named after its file stem.
+/// A missing directory simply has no templates.
+pub fn load_dir(dir: &Path) -> TemplateScan {
+    let mut scan = TemplateScan {
+        templates: Vec::new(),
+        skipped: Vec::new(),
+    };
+    let entries = match fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(_) => return scan,
+    };
+
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        if path.extension().is_none_or(|ext| ext != "md") {
+            continue;
+        }
+        match load_file(&path) {
+            Ok(t) => scan.templates.push(t),
+            Err(reason) => scan.skipped.push((path, reason)),
+        }
+    }
+    scan.templates.sort_by(|a, b| a.name.cmp(&b.name));
+    scan
+}
+
+fn load_file(path: &Path) -> Result<Template, String> {
+    let name = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .filter(|s| check_name(s).is_ok())
+        .ok_or_else(|| "file name is not a valid template name".to_string())?;
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    Ok(Template {
+        name: name.to_string(),
+        content: content.trim_end().to_string(),
+        source: TemplateSource::File(path.to_path_buf()),
+    })
+}
+
+/// Write every template to `out_dir` as `<name>.md`. Returns the templates
+/// written and the files in `dir` that couldn't be read.
+pub fn export_dir(conn: &Connection, dir: &Path, out_dir: &Path) -> anyhow::Result<TemplateScan> {
+    let scan = list_all(conn, dir)?;
+    fs::create_dir_all(out_dir)?;
+    for t in &scan.templates {
+        check_name(&t.name)?;
+        fs::write(out_dir.join(format!("{}.md", t.name)), format!("{}\n", t.content))?;
+    }
+    Ok(scan)
+}
+
+/// Store every `*.md` file in `src_dir` as a database template, replacing
+/// existing ones of the same name. Returns the imported templates and the
+/// files that couldn't be read.
+pub fn import_dir(conn: &Connection, src_dir: &Path) -> anyhow::Result<TemplateScan> {
+    if !src_dir.is_dir() {
+        anyhow::bail!("{} is not a directory", src_dir.display());
+    }
 
-    // Replace built-in variables
+    let scan = load_dir(src_dir);
+    for t in &scan.templates {
+        create(conn, &t.name, &t.content)?;
+    }
+    Ok(scan)
+}
+
+/// Render a template body. Built-in and user variables are always
+/// available, along with any `extra` values the caller supplies; when the
+/// template uses `{{ }}` tags, the store is exposed too: `open_todos`,
+/// `overdue_todos`, `recent_notes`, `tags` and `projects`.
+pub fn render(
+    conn: &Connection,
+    template_content: &str,
+    vars: &[(&str, &str)],
+    extra: serde_json::Map<String, serde_json::Value>,
+) -> anyhow::Result<engine::Rendered> {
     let now = Local::now();
-    result = result.replace("{date}", &now.format("%Y-%m-%d").to_string());
-    result = result.replace("{time}", &now.format("%H:%M").to_string());
-    result = result.replace("{datetime}", &now.format("%Y-%m-%d %H:%M").to_string());
+    let date = now.format("%Y-%m-%d").to_string();
+    let time = now.format("%H:%M").to_string();
+    let datetime = now.format("%Y-%m-%d %H:%M").to_string();
+
+    let mut context = engine::context_from(&[
+        ("date", date.as_str()),
+        ("time", time.as_str()),
+        ("datetime", datetime.as_str()),
+    ]);
+    context.extend(engine::context_from(vars));
+    context.extend(extra);
+
+    if template_content.contains("{{") {
+        let open = todo::list_todos(
+            conn,
+            &TodoFilter {
+                pending_only: true,
+                ..Default::default()
+            },
+        )?;
+        let now_ts = now.timestamp();
+        let overdue: Vec<&todo::Todo> = open
+            .iter()
+            .filter(|t| t.due_date.is_some_and(|d| d.timestamp() < now_ts))
+            .collect();
 
-    // Replace custom variables
-    for (key, value) in vars {
-        result = result.replace(&format!("{{{}}}", key), value);
+        context.insert("overdue_todos".into(), serde_json::to_value(&overdue)?);
+        context.insert("open_todos".into(), serde_json::to_value(&open)?);
+        context.insert(
+            "recent_notes".into(),
+            serde_json::to_value(note::list(conn, 10, None, None, false)?)?,
+        );
+        context.insert("tags".into(), serde_json::to_value(tags::list_all(conn)?)?);
+        context.insert(
+            "projects".into(),
+            serde_json::to_value(todo::project_progress(conn)?)?,
+        );
     }
 
-    result
+    engine::render(template_content, &serde_json::Value::Object(context))
 }
@@ -0,0 +1,163 @@
+use chrono::Local;
+use rusqlite::{params, Connection, Result as SqlResult};
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct RunningTimer {
+    pub todo_id: i64,
+    pub task: String,
+    pub started_at: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StoppedTimer {
+    pub todo_id: i64,
+    pub task: String,
+    pub seconds: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReportRow {
+    pub key: String,
+    pub seconds: i64,
+}
+
+pub enum StartOutcome {
+    Started { previous: Option<StoppedTimer> },
+    AlreadyRunning,
+    NotFound,
+}
+
+#[derive(Clone, Copy)]
+pub enum GroupBy {
+    Project,
+    Task,
+    Tag,
+    Day,
+}
+
+impl GroupBy {
+    pub fn parse(s: &str) -> Option<GroupBy> {
+        match s {
+            "project" => Some(GroupBy::Project),
+            "task" | "todo" => Some(GroupBy::Task),
+            "tag" => Some(GroupBy::Tag),
+            "day" | "date" => Some(GroupBy::Day),
+            _ => None,
+        }
+    }
+}
+
+pub fn running(conn: &Connection) -> SqlResult<Option<RunningTimer>> {
+    let mut stmt = conn.prepare(
+        "SELECT e.todo_id, t.task, e.started_at FROM time_entries e \
+         JOIN todos t ON t.id = e.todo_id WHERE e.ended_at IS NULL",
+    )?;
+    let mut rows = stmt.query([])?;
+    if let Some(row) = rows.next()? {
+        Ok(Some(RunningTimer {
+            todo_id: row.get(0)?,
+            task: row.get(1)?,
+            started_at: row.get(2)?,
+        }))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Start timing a TODO. Only one timer runs at a time, so any running timer
+/// is stopped first and reported back.
+pub fn start(conn: &Connection, todo_id: i64) -> SqlResult<StartOutcome> {
+    let exists: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM todos WHERE id = ?1",
+        params![todo_id],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        return Ok(StartOutcome::NotFound);
+    }
+
+    if running(conn)?.is_some_and(|t| t.todo_id == todo_id) {
+        return Ok(StartOutcome::AlreadyRunning);
+    }
+
+    let stopped = stop(conn)?;
+    conn.execute(
+        "INSERT INTO time_entries (todo_id, started_at) VALUES (?1, ?2)",
+        params![todo_id, Local::now().timestamp()],
+    )?;
+    Ok(StartOutcome::Started { previous: stopped })
+}
+
+pub fn stop(conn: &Connection) -> SqlResult<Option<StoppedTimer>> {
+    let timer = match running(conn)? {
+        Some(t) => t,
+        None => return Ok(None),
+    };
+
+    let now = Local::now().timestamp();
+    conn.execute(
+        "UPDATE time_entries SET ended_at = ?1 WHERE ended_at IS NULL",
+        params![now],
+    )?;
+
+    Ok(Some(StoppedTimer {
+        todo_id: timer.todo_id,
+        task: timer.task,
+        seconds: now - timer.started_at,
+    }))
+}
+
+/// Total tracked time per group for entries started at or after `since`.
+/// A running timer counts up to now.
+pub fn report(conn: &Connection, since: i64, by: GroupBy) -> SqlResult<Vec<ReportRow>> {
+    let (key, join) = match by {
+        GroupBy::Project => ("COALESCE(t.project, '(no project)')", ""),
+        GroupBy::Task => ("'#' || t.id || ' ' || t.task", ""),
+        GroupBy::Tag => (
+            "COALESCE(g.tag, '(untagged)')",
+            "LEFT JOIN todo_tags g ON g.todo_id = t.id",
+        ),
+        GroupBy::Day => ("date(e.started_at, 'unixepoch', 'localtime')", ""),
+    };
+    let order = match by {
+        GroupBy::Day => "k ASC",
+        _ => "secs DESC",
+    };
+
+    let query = format!(
+        "SELECT {} AS k, SUM(COALESCE(e.ended_at, ?1) - e.started_at) AS secs \
+         FROM time_entries e JOIN todos t ON t.id = e.todo_id {} \
+         WHERE e.started_at >= ?2 GROUP BY k ORDER BY {}",
+        key, join, order
+    );
+
+    let mut stmt = conn.prepare(&query)?;
+    let rows = stmt.query_map(params![Local::now().timestamp(), since], |row| {
+        Ok(ReportRow {
+            key: row.get(0)?,
+            seconds: row.get(1)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Time tracked on one TODO, counting a running timer up to now.
+pub fn tracked(conn: &Connection, todo_id: i64) -> SqlResult<i64> {
+    conn.query_row(
+        "SELECT COALESCE(SUM(COALESCE(ended_at, ?1) - started_at), 0) \
+         FROM time_entries WHERE todo_id = ?2",
+        params![Local::now().timestamp(), todo_id],
+        |row| row.get(0),
+    )
+}
+
+/// Total tracked time for entries started at or after `since`.
+pub fn total(conn: &Connection, since: i64) -> SqlResult<i64> {
+    conn.query_row(
+        "SELECT COALESCE(SUM(COALESCE(ended_at, ?1) - started_at), 0) \
+         FROM time_entries WHERE started_at >= ?2",
+        params![Local::now().timestamp(), since],
+        |row| row.get(0),
+    )
+}
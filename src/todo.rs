@@ -2,6 +2,7 @@ use chrono::{DateTime, Local, NaiveDate};
 use rusqlite::{params, Connection, Result as SqlResult};
 use serde::Serialize;
 
+use crate::note;
 use crate::utils::timestamp_to_local;
 
 #[derive(Debug, Serialize)]
@@ -9,9 +10,65 @@ pub struct Todo {
     pub id: i64,
     pub task: String,
     pub completed: bool,
+    pub status: String,
     pub priority: String,
     pub due_date: Option<DateTime<Local>>,
     pub created_at: DateTime<Local>,
+    pub project: Option<String>,
+    pub tags: Vec<String>,
+    pub note_id: Option<i64>,
+    /// Pending TODOs that must be finished before this one can start
+    pub blocked_by: Vec<i64>,
+    /// Seconds tracked with `todo start`/`stop`, including a running timer
+    pub tracked_secs: i64,
+    /// Hidden from default lists until this time
+    pub defer_until: Option<DateTime<Local>>,
+    pub completed_at: Option<DateTime<Local>>,
+    pub archived: bool,
+}
+
+impl Todo {
+    pub fn is_blocked(&self) -> bool {
+        !self.completed && !self.blocked_by.is_empty()
+    }
+
+    pub fn is_deferred(&self) -> bool {
+        !self.completed && self.defer_until.is_some_and(|d| d > Local::now())
+    }
+}
+
+/// Statuses every board understands. `done` and `cancelled` close a TODO;
+/// any other status (including custom board columns) keeps it open.
+pub const STATUSES: [&str; 5] = ["todo", "doing", "waiting", "done", "cancelled"];
+
+pub fn is_closed_status(status: &str) -> bool {
+    matches!(status, "done" | "cancelled")
+}
+
+pub enum BlockOutcome {
+    Blocked,
+    NotFound(i64),
+    Cycle,
+}
+
+/// A Markdown checkbox (`- [ ] task` / `- [x] task`) found in a note.
+#[derive(Debug, PartialEq)]
+pub struct Checkbox {
+    pub task: String,
+    pub checked: bool,
+}
+
+const TODO_COLUMNS: &str =
+    "t.id, t.task, t.completed, t.status, t.priority, t.due_date, t.created_at, t.project, t.note_id, \
+     t.defer_until, t.completed_at, t.archived, \
+     (SELECT COALESCE(SUM(COALESCE(e.ended_at, strftime('%s', 'now')) - e.started_at), 0) \
+      FROM time_entries e WHERE e.todo_id = t.id)";
+
+#[derive(Debug, Serialize)]
+pub struct ProjectProgress {
+    pub project: String,
+    pub total: i64,
+    pub completed: i64,
 }
 
 pub fn add(
@@ -19,6 +76,9 @@ pub fn add(
     task: &str,
     priority: &str,
     due_date: Option<&str>,
+    project: Option<&str>,
+    tags: &[String],
+    defer_until: Option<i64>,
 ) -> SqlResult<i64> {
     let now = Local::now().timestamp();
 
@@ -33,58 +93,518 @@ pub fn add(
     });
 
     conn.execute(
-        "INSERT INTO todos (task, priority, due_date, created_at) VALUES (?1, ?2, ?3, ?4)",
-        params![task, priority, due_ts, now],
+        "INSERT INTO todos (task, priority, due_date, created_at, project, defer_until) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![task, priority, due_ts, now, project, defer_until],
+    )?;
+
+    let todo_id = conn.last_insert_rowid();
+
+    for tag in tags {
+        conn.execute(
+            "INSERT INTO todo_tags (todo_id, tag) VALUES (?1, ?2)",
+            params![todo_id, tag.trim()],
+        )?;
+    }
+
+    Ok(todo_id)
+}
+
+/// Insert a fully specified TODO, e.g. one read from an import. The `id`,
+/// `note_id`, `blocked_by` and `tracked_secs` fields are ignored.
+pub fn insert(conn: &Connection, todo: &Todo) -> SqlResult<i64> {
+    conn.execute(
+        "INSERT INTO todos (task, completed, status, priority, due_date, created_at, project, \
+         defer_until, completed_at, archived) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            todo.task,
+            todo.completed,
+            todo.status,
+            todo.priority,
+            todo.due_date.map(|d| d.timestamp()),
+            todo.created_at.timestamp(),
+            todo.project,
+            todo.defer_until.map(|d| d.timestamp()),
+            todo.completed_at.map(|d| d.timestamp()),
+            todo.archived,
+        ],
     )?;
 
-    Ok(conn.last_insert_rowid())
+    let todo_id = conn.last_insert_rowid();
+
+    for tag in &todo.tags {
+        conn.execute(
+            "INSERT INTO todo_tags (todo_id, tag) VALUES (?1, ?2)",
+            params![todo_id, tag.trim()],
+        )?;
+    }
+
+    Ok(todo_id)
 }
 
-pub fn list_todos(conn: &Connection, pending_only: bool) -> SqlResult<Vec<Todo>> {
-    let query = if pending_only {
-        "SELECT id, task, completed, priority, due_date, created_at FROM todos WHERE completed = 0 ORDER BY \
-         CASE priority WHEN 'high' THEN 0 WHEN 'medium' THEN 1 WHEN 'low' THEN 2 ELSE 3 END, \
-         COALESCE(due_date, 9999999999) ASC"
+pub struct ImportReport {
+    pub imported: usize,
+    pub skipped: usize,
+}
+
+/// Insert `todos` in one transaction, skipping tasks that already exist, or
+/// came earlier in `todos`, with the same description and creation date.
+/// A dry run is rolled back.
+pub fn import_deduped(conn: &Connection, todos: &[Todo], dry_run: bool) -> SqlResult<ImportReport> {
+    let filter = TodoFilter {
+        include_deferred: true,
+        archived: None,
+        ..Default::default()
+    };
+    let mut existing: Vec<(String, NaiveDate)> = list_todos(conn, &filter)?
+        .into_iter()
+        .map(|t| (t.task, t.created_at.date_naive()))
+        .collect();
+
+    let tx = conn.unchecked_transaction()?;
+    let mut report = ImportReport {
+        imported: 0,
+        skipped: 0,
+    };
+    for t in todos {
+        let key = (t.task.clone(), t.created_at.date_naive());
+        if existing.contains(&key) {
+            report.skipped += 1;
+            continue;
+        }
+        insert(&tx, t)?;
+        existing.push(key);
+        report.imported += 1;
+    }
+    if dry_run {
+        tx.rollback()?;
+    } else {
+        tx.commit()?;
+    }
+    Ok(report)
+}
+
+/// Which TODOs `list_todos` returns. The default matches `todo list`:
+/// everything except archived TODOs and open TODOs deferred past now.
+pub struct TodoFilter<'a> {
+    pub pending_only: bool,
+    pub project: Option<&'a str>,
+    pub tag: Option<&'a str>,
+    pub include_deferred: bool,
+    /// `Some(false)` hides archived TODOs, `Some(true)` shows only archived
+    /// ones, and `None` shows both
+    pub archived: Option<bool>,
+}
+
+impl Default for TodoFilter<'_> {
+    fn default() -> Self {
+        TodoFilter {
+            pending_only: false,
+            project: None,
+            tag: None,
+            include_deferred: false,
+            archived: Some(false),
+        }
+    }
+}
+
+/// List TODOs by priority and due date. Deferred TODOs, when included,
+/// sort after actionable ones.
+pub fn list_todos(conn: &Connection, filter: &TodoFilter) -> SqlResult<Vec<Todo>> {
+    let mut conditions = Vec::new();
+    let mut param_values: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+
+    let now = Local::now().timestamp();
+    param_values.push(Box::new(now));
+
+    if filter.pending_only {
+        conditions.push("t.completed = 0".to_string());
+    }
+
+    if !filter.include_deferred {
+        conditions.push(
+            "(t.completed = 1 OR t.defer_until IS NULL OR t.defer_until <= ?1)".to_string(),
+        );
+    }
+
+    match filter.archived {
+        Some(true) => conditions.push("t.archived = 1".to_string()),
+        Some(false) => conditions.push("t.archived = 0".to_string()),
+        None => {}
+    }
+
+    if let Some(p) = filter.project {
+        conditions.push(format!("t.project = ?{}", param_values.len() + 1));
+        param_values.push(Box::new(p.to_string()));
+    }
+
+    if let Some(tg) = filter.tag {
+        conditions.push(format!(
+            "t.id IN (SELECT todo_id FROM todo_tags WHERE tag = ?{})",
+            param_values.len() + 1
+        ));
+        param_values.push(Box::new(tg.to_string()));
+    }
+
+    let where_clause = if conditions.is_empty() {
+        String::new()
     } else {
-        "SELECT id, task, completed, priority, due_date, created_at FROM todos ORDER BY \
-         CASE priority WHEN 'high' THEN 0 WHEN 'medium' THEN 1 WHEN 'low' THEN 2 ELSE 3 END, \
-         COALESCE(due_date, 9999999999) ASC"
+        format!("WHERE {}", conditions.join(" AND "))
     };
 
+    let query = format!(
+        "SELECT {} FROM todos t {} ORDER BY \
+         CASE WHEN t.completed = 0 AND t.defer_until > ?1 THEN 1 ELSE 0 END, \
+         CASE t.priority WHEN 'high' THEN 0 WHEN 'medium' THEN 1 WHEN 'low' THEN 2 ELSE 3 END, \
+         COALESCE(t.due_date, 9999999999) ASC, \
+         COALESCE(t.defer_until, 0) ASC",
+        TODO_COLUMNS, where_clause
+    );
+
+    let params_ref: Vec<&dyn rusqlite::types::ToSql> =
+        param_values.iter().map(|p| p.as_ref()).collect();
+
+    query_todos(conn, &query, params_ref.as_slice())
+}
+
+/// Pending TODOs due on or before `until`, soonest first.
+pub fn list_due_until(conn: &Connection, until: i64) -> SqlResult<Vec<Todo>> {
+    let query = format!(
+        "SELECT {} FROM todos t WHERE t.completed = 0 AND t.due_date IS NOT NULL \
+         AND t.due_date <= ?1 ORDER BY t.due_date ASC, \
+         CASE t.priority WHEN 'high' THEN 0 WHEN 'medium' THEN 1 WHEN 'low' THEN 2 ELSE 3 END",
+        TODO_COLUMNS
+    );
+    query_todos(conn, &query, &[&until])
+}
+
+pub fn get_by_id(conn: &Connection, id: i64) -> SqlResult<Option<Todo>> {
+    let query = format!("SELECT {} FROM todos t WHERE t.id = ?1", TODO_COLUMNS);
+    Ok(query_todos(conn, &query, &[&id])?.pop())
+}
+
+/// TODOs extracted from a note's checkboxes, in creation order.
+pub fn list_for_note(conn: &Connection, note_id: i64) -> SqlResult<Vec<Todo>> {
+    let query = format!(
+        "SELECT {} FROM todos t WHERE t.note_id = ?1 ORDER BY t.id",
+        TODO_COLUMNS
+    );
+    query_todos(conn, &query, &[&note_id])
+}
+
+fn query_todos(
+    conn: &Connection,
+    query: &str,
+    params: &[&dyn rusqlite::types::ToSql],
+) -> SqlResult<Vec<Todo>> {
     let mut stmt = conn.prepare(query)?;
-    let rows = stmt.query_map([], |row| {
+    let rows = stmt.query_map(params, |row| {
         Ok(Todo {
             id: row.get(0)?,
             task: row.get(1)?,
             completed: row.get(2)?,
-            priority: row.get::<_, String>(3)?,
-            due_date: row
-                .get::<_, Option<i64>>(4)?
-                .map(|ts| timestamp_to_local(ts)),
-            created_at: timestamp_to_local(row.get(5)?),
+            status: row.get(3)?,
+            priority: row.get::<_, String>(4)?,
+            due_date: row.get::<_, Option<i64>>(5)?.map(timestamp_to_local),
+            created_at: timestamp_to_local(row.get(6)?),
+            project: row.get(7)?,
+            tags: Vec::new(),
+            note_id: row.get(8)?,
+            blocked_by: Vec::new(),
+            defer_until: row.get::<_, Option<i64>>(9)?.map(timestamp_to_local),
+            completed_at: row.get::<_, Option<i64>>(10)?.map(timestamp_to_local),
+            archived: row.get(11)?,
+            tracked_secs: row.get(12)?,
         })
     })?;
 
-    rows.collect()
+    let mut todos = Vec::new();
+    for row in rows {
+        let mut todo = row?;
+        todo.tags = get_tags_for_todo(conn, todo.id)?;
+        todo.blocked_by = get_open_blockers(conn, todo.id)?;
+        todos.push(todo);
+    }
+
+    Ok(todos)
 }
 
 pub fn mark_done(conn: &Connection, id: i64) -> SqlResult<bool> {
+    set_status(conn, id, "done")
+}
+
+/// Move a TODO to a workflow status, keeping `completed` and any linked note
+/// checkbox in step with it.
+pub fn set_status(conn: &Connection, id: i64, status: &str) -> SqlResult<bool> {
+    let closed = is_closed_status(status);
     let affected = conn.execute(
-        "UPDATE todos SET completed = 1 WHERE id = ?1",
-        params![id],
+        "UPDATE todos SET status = ?1, completed = ?2, \
+         completed_at = CASE WHEN ?2 THEN COALESCE(completed_at, ?3) ELSE NULL END \
+         WHERE id = ?4",
+        params![status, closed, Local::now().timestamp(), id],
+    )?;
+    if affected > 0 {
+        check_in_note(conn, id, closed)?;
+    }
+    Ok(affected > 0)
+}
+
+/// Hide a TODO from default lists until `until` (a Unix timestamp).
+pub fn snooze(conn: &Connection, id: i64, until: i64) -> SqlResult<bool> {
+    let affected = conn.execute(
+        "UPDATE todos SET defer_until = ?1 WHERE id = ?2",
+        params![until, id],
+    )?;
+    Ok(affected > 0)
+}
+
+pub fn count_in_status(conn: &Connection, status: &str) -> SqlResult<i64> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM todos WHERE status = ?1",
+        params![status],
+        |row| row.get(0),
+    )
+}
+
+/// TODOs grouped by the given statuses, in column order.
+pub fn board(
+    conn: &Connection,
+    statuses: &[String],
+    project: Option<&str>,
+) -> SqlResult<Vec<(String, Vec<Todo>)>> {
+    let filter = TodoFilter {
+        project,
+        ..Default::default()
+    };
+    let mut remaining = list_todos(conn, &filter)?;
+    let mut columns = Vec::new();
+    for status in statuses {
+        let (column, rest): (Vec<Todo>, Vec<Todo>) =
+            remaining.into_iter().partition(|t| &t.status == status);
+        remaining = rest;
+        columns.push((status.clone(), column));
+    }
+    Ok(columns)
+}
+
+/// Parse Markdown task-list items from note content. Empty items such as the
+/// `- [ ] ` placeholder in the daily template are ignored.
+pub fn extract_checkboxes(content: &str) -> Vec<Checkbox> {
+    content.lines().filter_map(parse_checkbox).collect()
+}
+
+pub fn parse_checkbox(line: &str) -> Option<Checkbox> {
+    let rest = line.trim_start().strip_prefix(['-', '*', '+'])?;
+    let rest = rest.strip_prefix(' ')?;
+    let checked = match rest.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    let task = rest[3..].trim();
+    if task.is_empty() {
+        return None;
+    }
+    Some(Checkbox {
+        task: task.to_string(),
+        checked,
+    })
+}
+
+/// Bring a note's linked TODOs in line with its checkboxes: new items become
+/// TODOs, ticked items complete them, and removed items delete them.
+///
+/// Items are matched to TODOs by text, and the rest in order, so editing an
+/// item's wording keeps its TODO along with its project, tags, priority,
+/// dates, dependencies and tracked time.
+pub fn sync_from_note(conn: &Connection, note_id: i64, content: &str) -> SqlResult<()> {
+    let mut checkboxes = extract_checkboxes(content);
+    let mut seen: Vec<String> = Vec::new();
+    checkboxes.retain(|c| {
+        let first = !seen.contains(&c.task);
+        seen.push(c.task.clone());
+        first
+    });
+    let existing = list_for_note(conn, note_id)?;
+
+    let mut unmatched = existing
+        .iter()
+        .filter(|t| !checkboxes.iter().any(|c| c.task == t.task));
+
+    for checkbox in &checkboxes {
+        let todo = match existing.iter().find(|t| t.task == checkbox.task) {
+            Some(t) => Some(t),
+            None => unmatched.next(),
+        };
+
+        match todo {
+            Some(todo) => {
+                if todo.task != checkbox.task {
+                    conn.execute(
+                        "UPDATE todos SET task = ?1 WHERE id = ?2",
+                        params![checkbox.task, todo.id],
+                    )?;
+                }
+                // A TODO closed as cancelled (or any status but done) before
+                // its checkbox was ticked along with it stays closed
+                let closed_unticked = todo.completed && todo.status != "done" && !checkbox.checked;
+                if todo.completed != checkbox.checked && !closed_unticked {
+                    let status = if checkbox.checked { "done" } else { "todo" };
+                    let completed_at = checkbox.checked.then(|| Local::now().timestamp());
+                    conn.execute(
+                        "UPDATE todos SET completed = ?1, status = ?2, completed_at = ?3 WHERE id = ?4",
+                        params![checkbox.checked, status, completed_at, todo.id],
+                    )?;
+                }
+            }
+            None => {
+                let status = if checkbox.checked { "done" } else { "todo" };
+                let now = Local::now().timestamp();
+                conn.execute(
+                    "INSERT INTO todos (task, completed, status, priority, created_at, note_id, completed_at) \
+                     VALUES (?1, ?2, ?3, 'medium', ?4, ?5, ?6)",
+                    params![
+                        checkbox.task,
+                        checkbox.checked,
+                        status,
+                        now,
+                        note_id,
+                        checkbox.checked.then_some(now)
+                    ],
+                )?;
+            }
+        }
+    }
+
+    // A TODO whose checkbox was removed keeps its tracked time and
+    // dependencies; it just loses the link, as when its note is deleted
+    for todo in unmatched {
+        conn.execute("UPDATE todos SET note_id = NULL WHERE id = ?1", params![todo.id])?;
+    }
+
+    Ok(())
+}
+
+/// Tick or untick the checkbox backing a linked TODO in its source note.
+fn check_in_note(conn: &Connection, id: i64, checked: bool) -> SqlResult<()> {
+    let todo = match get_by_id(conn, id)? {
+        Some(t) => t,
+        None => return Ok(()),
+    };
+    let note_id = match todo.note_id {
+        Some(n) => n,
+        None => return Ok(()),
+    };
+    let existing = match note::get_by_id(conn, note_id)? {
+        Some(n) => n,
+        None => return Ok(()),
+    };
+
+    let mut changed = false;
+    let lines: Vec<String> = existing
+        .content
+        .lines()
+        .map(|line| match parse_checkbox(line) {
+            Some(c) if c.task == todo.task && c.checked != checked => {
+                changed = true;
+                let mark = if checked { "[x]" } else { "[ ]" };
+                let pos = line.find('[').unwrap_or(0);
+                format!("{}{}{}", &line[..pos], mark, &line[pos + 3..])
+            }
+            _ => line.to_string(),
+        })
+        .collect();
+
+    if changed {
+        note::update(conn, note_id, &lines.join("\n"))?;
+    }
+
+    Ok(())
+}
+
+/// Actionable TODOs: pending and not waiting on any other pending TODO,
+/// ordered by priority and then by due date.
+pub fn next_actions(conn: &Connection, limit: usize) -> SqlResult<Vec<Todo>> {
+    let filter = TodoFilter {
+        pending_only: true,
+        ..Default::default()
+    };
+    let todos = list_todos(conn, &filter)?;
+    Ok(todos
+        .into_iter()
+        .filter(|t| !t.is_blocked())
+        .take(limit)
+        .collect())
+}
+
+/// Record that `id` cannot start until `on` is done, refusing dependencies
+/// that would close a cycle.
+pub fn block(conn: &Connection, id: i64, on: i64) -> SqlResult<BlockOutcome> {
+    for todo_id in [id, on] {
+        let exists: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM todos WHERE id = ?1",
+            params![todo_id],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            return Ok(BlockOutcome::NotFound(todo_id));
+        }
+    }
+
+    if id == on || depends_on(conn, on, id)? {
+        return Ok(BlockOutcome::Cycle);
+    }
+
+    conn.execute(
+        "INSERT OR IGNORE INTO todo_deps (todo_id, blocked_by) VALUES (?1, ?2)",
+        params![id, on],
+    )?;
+    Ok(BlockOutcome::Blocked)
+}
+
+pub fn unblock(conn: &Connection, id: i64, on: i64) -> SqlResult<bool> {
+    let affected = conn.execute(
+        "DELETE FROM todo_deps WHERE todo_id = ?1 AND blocked_by = ?2",
+        params![id, on],
     )?;
     Ok(affected > 0)
 }
 
+/// Whether `id` transitively depends on `target`.
+fn depends_on(conn: &Connection, id: i64, target: i64) -> SqlResult<bool> {
+    conn.query_row(
+        "WITH RECURSIVE chain(id) AS ( \
+             SELECT blocked_by FROM todo_deps WHERE todo_id = ?1 \
+             UNION \
+             SELECT d.blocked_by FROM todo_deps d JOIN chain c ON d.todo_id = c.id \
+         ) \
+         SELECT COUNT(*) > 0 FROM chain WHERE id = ?2",
+        params![id, target],
+        |row| row.get(0),
+    )
+}
+
+/// Delete a TODO along with its tags, dependencies and tracked time.
 pub fn delete(conn: &Connection, id: i64) -> SqlResult<bool> {
+    conn.execute("DELETE FROM todo_tags WHERE todo_id = ?1", params![id])?;
+    conn.execute(
+        "DELETE FROM todo_deps WHERE todo_id = ?1 OR blocked_by = ?1",
+        params![id],
+    )?;
+    conn.execute("DELETE FROM time_entries WHERE todo_id = ?1", params![id])?;
     let affected = conn.execute("DELETE FROM todos WHERE id = ?1", params![id])?;
     Ok(affected > 0)
 }
 
+/// Total, completed and pending counts, leaving out archived TODOs.
 pub fn count_stats(conn: &Connection) -> SqlResult<(i64, i64, i64)> {
-    let total: i64 = conn.query_row("SELECT COUNT(*) FROM todos", [], |row| row.get(0))?;
+    let total: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM todos WHERE archived = 0",
+        [],
+        |row| row.get(0),
+    )?;
     let completed: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM todos WHERE completed = 1",
+        "SELECT COUNT(*) FROM todos WHERE completed = 1 AND archived = 0",
         [],
         |row| row.get(0),
     )?;
@@ -92,6 +612,41 @@ pub fn count_stats(conn: &Connection) -> SqlResult<(i64, i64, i64)> {
     Ok((total, completed, pending))
 }
 
+pub fn count_archived(conn: &Connection) -> SqlResult<i64> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM todos WHERE archived = 1",
+        [],
+        |row| row.get(0),
+    )
+}
+
+/// Archive closed TODOs finished before `cutoff`. TODOs closed before
+/// completion times were recorded fall back to their creation time.
+pub fn archive_completed_before(conn: &Connection, cutoff: i64) -> SqlResult<usize> {
+    conn.execute(
+        "UPDATE todos SET archived = 1 \
+         WHERE completed = 1 AND archived = 0 AND COALESCE(completed_at, created_at) < ?1",
+        params![cutoff],
+    )
+}
+
+pub fn project_progress(conn: &Connection) -> SqlResult<Vec<ProjectProgress>> {
+    let mut stmt = conn.prepare(
+        "SELECT project, COUNT(*), SUM(CASE WHEN completed = 1 THEN 1 ELSE 0 END) \
+         FROM todos WHERE project IS NOT NULL AND archived = 0 GROUP BY project ORDER BY project",
+    )?;
+
+    let rows = stmt.query_map([], |row| {
+        Ok(ProjectProgress {
+            project: row.get(0)?,
+            total: row.get(1)?,
+            completed: row.get(2)?,
+        })
+    })?;
+
+    rows.collect()
+}
+
 pub fn count_overdue(conn: &Connection) -> SqlResult<i64> {
     let now = Local::now().timestamp();
     conn.query_row(
@@ -123,3 +678,55 @@ pub fn count_due_today(conn: &Connection) -> SqlResult<i64> {
         |row| row.get(0),
     )
 }
+
+fn get_tags_for_todo(conn: &Connection, todo_id: i64) -> SqlResult<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT tag FROM todo_tags WHERE todo_id = ?1")?;
+    let tags = stmt
+        .query_map(params![todo_id], |row| row.get(0))?
+        .collect::<SqlResult<Vec<String>>>()?;
+    Ok(tags)
+}
+
+fn get_open_blockers(conn: &Connection, todo_id: i64) -> SqlResult<Vec<i64>> {
+    let mut stmt = conn.prepare(
+        "SELECT d.blocked_by FROM todo_deps d JOIN todos b ON b.id = d.blocked_by \
+         WHERE d.todo_id = ?1 AND b.completed = 0 ORDER BY d.blocked_by",
+    )?;
+    let ids = stmt
+        .query_map(params![todo_id], |row| row.get(0))?
+        .collect::<SqlResult<Vec<i64>>>()?;
+    Ok(ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removing_a_checkbox_keeps_its_todo_and_time() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::initialize(&conn).unwrap();
+        let note_id = note::add(&conn, "- [ ] write report\n- [ ] call Ana", &[], None, false).unwrap();
+        let todo = list_for_note(&conn, note_id)
+            .unwrap()
+            .into_iter()
+            .find(|t| t.task == "call Ana")
+            .unwrap();
+        crate::timelog::start(&conn, todo.id).unwrap();
+        crate::timelog::stop(&conn).unwrap();
+
+        note::update(&conn, note_id, "- [ ] write report").unwrap();
+
+        let kept = get_by_id(&conn, todo.id).unwrap().unwrap();
+        assert_eq!(kept.note_id, None);
+        let entries: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM time_entries WHERE todo_id = ?1",
+                params![todo.id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(entries, 1);
+        assert_eq!(list_for_note(&conn, note_id).unwrap().len(), 1);
+    }
+}
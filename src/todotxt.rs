@@ -0,0 +1,200 @@
+use chrono::{Local, NaiveDate};
+use rusqlite::{Connection, Result as SqlResult};
+
+use crate::todo::{self, is_closed_status, ImportReport, Todo, TodoFilter};
+use crate::utils::{day_bounds, timestamp_to_local};
+
+pub fn export(conn: &Connection) -> SqlResult<String> {
+    let filter = TodoFilter {
+        include_deferred: true,
+        archived: None,
+        ..Default::default()
+    };
+    let mut todos = todo::list_todos(conn, &filter)?;
+    todos.sort_by_key(|t| t.id);
+
+    let mut out = String::new();
+    for t in &todos {
+        out.push_str(&format_todo(t));
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Import every parseable line, skipping tasks that already exist with the
+/// same description and creation date.
+pub fn import(conn: &Connection, content: &str) -> SqlResult<ImportReport> {
+    let todos: Vec<Todo> = content.lines().filter_map(parse_line).collect();
+    todo::import_deduped(conn, &todos, false)
+}
+
+pub fn format_todo(t: &Todo) -> String {
+    let mut parts: Vec<String> = Vec::new();
+
+    let letter = match t.priority.as_str() {
+        "high" => "A",
+        "low" => "C",
+        _ => "B",
+    };
+
+    if t.completed {
+        // todo.txt only allows a creation date after a completion date, so
+        // TODOs finished before completion times were kept use the creation
+        // date for both
+        let done = t.completed_at.unwrap_or(t.created_at);
+        parts.push("x".to_string());
+        parts.push(done.format("%Y-%m-%d").to_string());
+    } else {
+        parts.push(format!("({})", letter));
+    }
+
+    parts.push(t.created_at.format("%Y-%m-%d").to_string());
+    parts.push(t.task.clone());
+
+    if let Some(ref project) = t.project {
+        parts.push(format!("+{}", project.replace(char::is_whitespace, "-")));
+    }
+    for tag in &t.tags {
+        let tag = tag.replace(char::is_whitespace, "-");
+        if tag.starts_with('+') {
+            parts.push(tag);
+        } else {
+            parts.push(format!("@{}", tag));
+        }
+    }
+
+    if let Some(due) = t.due_date {
+        parts.push(format!("due:{}", due.format("%Y-%m-%d")));
+    }
+    if let Some(start) = t.defer_until {
+        parts.push(format!("t:{}", start.format("%Y-%m-%d")));
+    }
+    if t.completed {
+        parts.push(format!("pri:{}", letter));
+    }
+
+    let default_status = if t.completed { "done" } else { "todo" };
+    if t.status != default_status {
+        parts.push(format!("status:{}", t.status));
+    }
+    if t.archived {
+        parts.push("archived:1".to_string());
+    }
+
+    parts.join(" ")
+}
+
+/// Parse one todo.txt line. `(A)`/`(B)`/`(C)` map to high/medium/low (later
+/// letters to low), `due:` and `t:` set the due and start dates, the first
+/// `+project` becomes the project and `@contexts` become tags. Extra
+/// `+projects` stay tags with their prefix, and `status:`/`pri:`/`archived:`
+/// carry the fields todo.txt has no syntax for, so `format_todo` output reads
+/// back unchanged.
+pub fn parse_line(line: &str) -> Option<Todo> {
+    let mut tokens = line.split_whitespace().peekable();
+
+    let completed = tokens.peek() == Some(&"x");
+    if completed {
+        tokens.next();
+    }
+
+    let mut completed_on = None;
+    if completed {
+        if let Some(date) = tokens.peek().and_then(|t| parse_date(t)) {
+            completed_on = Some(date);
+            tokens.next();
+        }
+    }
+
+    let mut priority = None;
+    if let Some(p) = tokens.peek().and_then(|t| parse_priority(t)) {
+        priority = Some(p);
+        tokens.next();
+    }
+
+    let mut created_on = None;
+    if let Some(date) = tokens.peek().and_then(|t| parse_date(t)) {
+        created_on = Some(date);
+        tokens.next();
+    }
+
+    let mut words = Vec::new();
+    let mut project = None;
+    let mut tags = Vec::new();
+    let mut due = None;
+    let mut defer = None;
+    let mut status = None;
+    let mut archived = false;
+
+    for token in tokens {
+        if let Some(p) = token.strip_prefix('+').filter(|p| !p.is_empty()) {
+            if project.is_none() {
+                project = Some(p.to_string());
+            } else {
+                tags.push(token.to_string());
+            }
+        } else if let Some(c) = token.strip_prefix('@').filter(|c| !c.is_empty()) {
+            tags.push(c.to_string());
+        } else if let Some(d) = token.strip_prefix("due:").and_then(parse_date) {
+            due = Some(d);
+        } else if let Some(d) = token.strip_prefix("t:").and_then(parse_date) {
+            defer = Some(d);
+        } else if let Some(p) = token
+            .strip_prefix("pri:")
+            .and_then(|p| parse_priority(&format!("({})", p)))
+        {
+            priority = Some(p);
+        } else if let Some(s) = token.strip_prefix("status:").filter(|s| !s.is_empty()) {
+            status = Some(s.to_string());
+        } else if let Some(a) = token.strip_prefix("archived:").filter(|a| matches!(*a, "0" | "1")) {
+            archived = a == "1";
+        } else {
+            words.push(token);
+        }
+    }
+
+    let task = words.join(" ");
+    if task.is_empty() {
+        return None;
+    }
+
+    let status = match status {
+        Some(s) if is_closed_status(&s) == completed => s,
+        _ if completed => "done".to_string(),
+        _ => "todo".to_string(),
+    };
+
+    Some(Todo {
+        id: 0,
+        task,
+        completed,
+        status,
+        priority: priority.unwrap_or("medium").to_string(),
+        due_date: due.map(|d| timestamp_to_local(day_bounds(d).1)),
+        created_at: created_on
+            .map(|d| timestamp_to_local(day_bounds(d).0))
+            .unwrap_or_else(Local::now),
+        project,
+        tags,
+        note_id: None,
+        blocked_by: Vec::new(),
+        tracked_secs: 0,
+        defer_until: defer.map(|d| timestamp_to_local(day_bounds(d).0)),
+        completed_at: completed_on.map(|d| timestamp_to_local(day_bounds(d).0)),
+        archived,
+    })
+}
+
+fn parse_date(s: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()
+}
+
+fn parse_priority(s: &str) -> Option<&'static str> {
+    let letter = s.strip_prefix('(')?.strip_suffix(')')?;
+    match letter {
+        "A" => Some("high"),
+        "B" => Some("medium"),
+        l if l.len() == 1 && l.chars().all(|c| c.is_ascii_uppercase()) => Some("low"),
+        _ => None,
+    }
+}
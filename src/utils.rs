@@ -1,4 +1,5 @@
-use chrono::{DateTime, Local, TimeZone};
+use chrono::{DateTime, Datelike, Days, Duration, Local, NaiveDate, TimeZone, Weekday};
+use serde::{Deserialize, Deserializer};
 
 /// Convert a Unix timestamp (seconds since epoch) to a local DateTime.
 ///
@@ -6,3 +7,215 @@ use chrono::{DateTime, Local, TimeZone};
 pub fn timestamp_to_local(ts: i64) -> DateTime<Local> {
     Local.timestamp_opt(ts, 0).single().unwrap_or_else(Local::now)
 }
+
+/// First and last second of a local calendar day as Unix timestamps.
+pub fn day_bounds(date: NaiveDate) -> (i64, i64) {
+    let start = date
+        .and_hms_opt(0, 0, 0)
+        .and_then(|dt| dt.and_local_timezone(Local).earliest())
+        .map(|dt| dt.timestamp())
+        .unwrap_or_default();
+    let end = date
+        .and_hms_opt(23, 59, 59)
+        .and_then(|dt| dt.and_local_timezone(Local).latest())
+        .map(|dt| dt.timestamp())
+        .unwrap_or_default();
+    (start, end)
+}
+
+/// Parse a span such as `45m`, `8h`, `3d` or `2w`. Spans too large to
+/// represent are `None`.
+pub fn parse_duration(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit())?;
+    let amount: i64 = s[..split].parse().ok()?;
+    match &s[split..] {
+        "m" | "min" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        "w" => Duration::try_weeks(amount),
+        _ => None,
+    }
+}
+
+/// Format a number of seconds as a compact duration like `2h 05m`.
+pub fn format_duration(secs: i64) -> String {
+    let hours = secs / 3600;
+    let minutes = (secs % 3600) / 60;
+    if hours > 0 {
+        format!("{}h {:02}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}s", secs.max(0))
+    }
+}
+
+/// Resolve a future point in time given as a span from now (`3d`, `2w`, `4h`)
+/// or a date (`YYYY-MM-DD`). Day and week spans land on the start of the day.
+pub fn parse_until(s: &str) -> Option<i64> {
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Some(day_bounds(date).0);
+    }
+
+    let span = parse_duration(s)?;
+    if span.num_seconds() % 86_400 == 0 {
+        let date = Local::now().date_naive().checked_add_signed(span)?;
+        Some(day_bounds(date).0)
+    } else {
+        Local::now().checked_add_signed(span).map(|dt| dt.timestamp())
+    }
+}
+
+/// Resolve a date relative to `today`: `YYYY-MM-DD`, `today`, `yesterday`,
+/// `tomorrow`, a signed span (`-2d`, `+1w`), or a weekday (`friday`,
+/// `last friday`, `next monday`). A bare weekday means its most recent
+/// occurrence, today included.
+pub fn parse_date_expr(s: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let s = s.trim().to_lowercase();
+    if let Ok(date) = NaiveDate::parse_from_str(&s, "%Y-%m-%d") {
+        return Some(date);
+    }
+
+    match s.as_str() {
+        "today" => return Some(today),
+        "yesterday" => return Some(today - Duration::days(1)),
+        "tomorrow" => return Some(today + Duration::days(1)),
+        _ => {}
+    }
+
+    // Spans past the calendar's range resolve to nothing rather than panic
+    if let Some(span) = s.strip_prefix('-') {
+        return parse_duration(span)
+            .filter(|d| d.num_seconds() % 86_400 == 0)
+            .and_then(|d| today.checked_sub_days(Days::new(d.num_days() as u64)));
+    }
+    if let Some(span) = s.strip_prefix('+') {
+        return parse_duration(span)
+            .filter(|d| d.num_seconds() % 86_400 == 0)
+            .and_then(|d| today.checked_add_days(Days::new(d.num_days() as u64)));
+    }
+
+    let (direction, day) = match s.split_once(' ') {
+        Some((dir @ ("last" | "next"), day)) => (dir, day.trim()),
+        _ => ("", s.as_str()),
+    };
+    let weekday: Weekday = day.parse().ok()?;
+    let back = (today.weekday().num_days_from_monday() + 7 - weekday.num_days_from_monday()) % 7;
+    match direction {
+        "last" => Some(today - Duration::days(if back == 0 { 7 } else { back as i64 })),
+        "next" => {
+            let ahead = (7 - back) % 7;
+            Some(today + Duration::days(if ahead == 0 { 7 } else { ahead as i64 }))
+        }
+        _ => Some(today - Duration::days(back as i64)),
+    }
+}
+
+/// Split a leading `---` YAML block from the rest of a Markdown document,
+/// returning `(yaml, body)`.
+pub fn split_front_matter(content: &str) -> Option<(&str, &str)> {
+    let rest = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))?;
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            let body = &rest[offset + line.len()..];
+            return Some((&rest[..offset], body.trim_start_matches(['\r', '\n'])));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// The first non-empty line of a note, without Markdown heading marks.
+pub fn note_title(content: &str) -> &str {
+    content
+        .lines()
+        .map(|l| l.trim_start_matches('#').trim())
+        .find(|l| !l.is_empty())
+        .unwrap_or("")
+}
+
+/// Lowercase `title`, keep letters and digits, and join the words with `-`.
+pub fn slugify(title: &str) -> Option<String> {
+    let mut slug = String::new();
+    for c in title.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+        if slug.chars().count() >= 60 {
+            break;
+        }
+    }
+    let slug = slug.trim_end_matches('-').to_string();
+    (!slug.is_empty()).then_some(slug)
+}
+
+/// Hashtags written in Markdown text (`#idea`, `#work/acme`), in order of
+/// first appearance. Headings, code, URL fragments and `[[Note#Heading]]`
+/// links are not tags, and neither are purely numeric ones like `#12`.
+pub fn inline_hashtags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    let mut in_fence = false;
+
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+
+        let mut in_code = false;
+        let mut prev = ' ';
+        let mut chars = line.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            if c == '`' {
+                in_code = !in_code;
+            } else if c == '#' && !in_code && prev.is_whitespace() {
+                let tag: String = line[i + 1..]
+                    .chars()
+                    .take_while(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '/'))
+                    .collect();
+                let tag = tag.trim_end_matches('/');
+                if tag.chars().any(|c| !c.is_ascii_digit()) && !tags.iter().any(|t| t == tag) {
+                    tags.push(tag.to_string());
+                }
+                while chars.peek().is_some_and(|&(_, c)| !c.is_whitespace()) {
+                    chars.next();
+                }
+            }
+            prev = c;
+        }
+    }
+    tags
+}
+
+/// Accept front matter tags written as `tags: meeting`, `tags: meeting, work`
+/// or `tags: [meeting, work]`, with or without leading `#`.
+pub fn deserialize_tags<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+        Empty(()),
+    }
+
+    let tags = match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(s) => s.split(',').map(|t| t.to_string()).collect(),
+        OneOrMany::Many(v) => v,
+        OneOrMany::Empty(()) => Vec::new(),
+    };
+    Ok(tags
+        .into_iter()
+        .map(|t| t.trim().trim_start_matches('#').to_string())
+        .filter(|t| !t.is_empty())
+        .collect())
+}